@@ -0,0 +1,72 @@
+use crate::ray::Ray;
+use crate::vec3::Point3;
+
+// Axis aligned bounding box
+#[derive(Debug, Copy, Clone)]
+pub struct Aabb {
+    pub minimum: Point3,
+    pub maximum: Point3,
+}
+
+impl Aabb {
+    // Box around two opposite corners given in any order
+    pub fn new(a: &Point3, b: &Point3) -> Self {
+        Self { minimum: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+               maximum: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)) }
+    }
+
+    // Box around everything, for objects without bounds
+    pub fn infinite() -> Self {
+        let inf: f64 = f64::INFINITY;
+        Self { minimum: Point3::new(-inf, -inf, -inf), maximum: Point3::new(inf, inf, inf) }
+    }
+
+    pub fn surrounding_box(&self, other: &Self) -> Self {
+        Self::new(&Point3::new(self.minimum.x.min(other.minimum.x), self.minimum.y.min(other.minimum.y), self.minimum.z.min(other.minimum.z)),
+                  &Point3::new(self.maximum.x.max(other.maximum.x), self.maximum.y.max(other.maximum.y), self.maximum.z.max(other.maximum.z)))
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.minimum.x.is_finite() && self.minimum.y.is_finite() && self.minimum.z.is_finite()
+            && self.maximum.x.is_finite() && self.maximum.y.is_finite() && self.maximum.z.is_finite()
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        if !self.is_finite() {
+            return Point3::default();
+        }
+        (self.minimum + self.maximum) * 0.5
+    }
+
+    // The eight corners, to bound a transformed box
+    pub fn corners(&self) -> [Point3; 8] {
+        let (a, b): (Point3, Point3) = (self.minimum, self.maximum);
+        [Point3::new(a.x, a.y, a.z), Point3::new(b.x, a.y, a.z), Point3::new(a.x, b.y, a.z), Point3::new(b.x, b.y, a.z),
+         Point3::new(a.x, a.y, b.z), Point3::new(b.x, a.y, b.z), Point3::new(a.x, b.y, b.z), Point3::new(b.x, b.y, b.z)]
+    }
+
+    // Slab test
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        if !self.is_finite() {
+            return true;
+        }
+        let mut t_min: f64 = t_min;
+        let mut t_max: f64 = t_max;
+        for (origin, direction, minimum, maximum) in [(r.origin.x, r.direction.x, self.minimum.x, self.maximum.x),
+                                                      (r.origin.y, r.direction.y, self.minimum.y, self.maximum.y),
+                                                      (r.origin.z, r.direction.z, self.minimum.z, self.maximum.z)] {
+            let inverse: f64 = 1.0 / direction;
+            let mut t0: f64 = (minimum - origin) * inverse;
+            let mut t1: f64 = (maximum - origin) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
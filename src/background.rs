@@ -0,0 +1,177 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::ImageError;
+use image::codecs::hdr::HdrDecoder;
+use rand::Rng;
+
+use crate::distribution::Distribution2D;
+use crate::image_texture::{ImageTexture, WrapMode};
+use crate::sky::Sky;
+use crate::vec3::{Vec3, Color};
+use crate::PRNG;
+
+pub(crate) fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// `image::open` tone maps Radiance files to 8 bits, so those are read with their own decoder
+fn load_linear(path: &Path) -> Result<ImageTexture, ImageError> {
+    let is_radiance: bool = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+    if !is_radiance {
+        return ImageTexture::load(path, false);
+    }
+    let decoder: HdrDecoder<BufReader<File>> = HdrDecoder::new(BufReader::new(File::open(path).map_err(ImageError::IoError)?))?;
+    let (width, height): (usize, usize) = (decoder.metadata().width as usize, decoder.metadata().height as usize);
+    let pixels: Vec<Color> = decoder.read_image_hdr()?.iter().map(|pixel| Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64)).collect();
+    Ok(ImageTexture::from_pixels(width, height, pixels))
+}
+
+// Inverse of the equirectangular layout for a map turned by `rotation` radians
+pub(crate) fn uv_to_direction(u: f64, v: f64, rotation: f64) -> Vec3 {
+    let theta: f64 = (1.0 - v) * PI;
+    let phi: f64 = u * 2.0 * PI + rotation;
+    Vec3::new(-theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+}
+
+// Equirectangular (latitude / longitude) HDR image around the scene, +y is up.
+// Directions are importance sampled by luminance, weighted by the solid angle of each row.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    image: ImageTexture,
+    distribution: Distribution2D,
+    // Turn around +y in radians
+    rotation: f64,
+    intensity: f64,
+}
+
+impl EnvironmentMap {
+    // Radiance `.hdr` and OpenEXR files hold linear values, nothing is decoded
+    pub fn load<P: AsRef<Path>>(path: P, rotation: f64, intensity: f64) -> Result<Self, ImageError> {
+        Ok(Self::from_image(load_linear(path.as_ref())?, rotation, intensity))
+    }
+
+    // Map of linear radiance, e.g. a baked procedural sky
+    pub fn from_image(image: ImageTexture, rotation: f64, intensity: f64) -> Self {
+        let (width, height): (usize, usize) = image.resolution();
+        let mut func: Vec<f64> = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta: f64 = (PI * (y as f64 + 0.5) / height as f64).sin();
+            for x in 0..width {
+                func.push(luminance(&image.pixel(x, y)).max(0.0) * sin_theta);
+            }
+        }
+        let distribution: Distribution2D = Distribution2D::new(&func, width, height);
+        Self { image, distribution, rotation: rotation.to_radians(), intensity }
+    }
+
+    // u = 0 towards -x and 0.25 towards +z, v = 0 straight down
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d: Vec3 = direction.normalize();
+        let theta: f64 = d.y.clamp(-1.0, 1.0).acos();
+        let phi: f64 = (-d.z).atan2(d.x) + PI - self.rotation;
+        ((phi / (2.0 * PI)).rem_euclid(1.0), 1.0 - theta / PI)
+    }
+
+    pub fn value(&self, direction: &Vec3) -> Color {
+        let (u, v): (f64, f64) = self.direction_to_uv(direction);
+        // Keep the bilinear lookup from wrapping over the poles
+        let half_texel: f64 = 0.5 / self.image.resolution().1.max(1) as f64;
+        self.image.sample(u, v.clamp(half_texel, 1.0 - half_texel), WrapMode::Repeat) * self.intensity
+    }
+
+    // Luminance arriving from the whole sphere, to weigh the map against other lights
+    pub fn power(&self) -> f64 {
+        let (width, height): (usize, usize) = self.image.resolution();
+        let solid_angle: f64 = 2.0 * PI * PI / (width * height) as f64;
+        let mut power: f64 = 0.0;
+        for y in 0..height {
+            let sin_theta: f64 = (PI * (y as f64 + 0.5) / height as f64).sin();
+            for x in 0..width {
+                power += luminance(&self.image.pixel(x, y)) * sin_theta * solid_angle;
+            }
+        }
+        power * self.intensity
+    }
+
+    // Solid angle density of `random`
+    pub fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let (u, v): (f64, f64) = self.direction_to_uv(direction);
+        let sin_theta: f64 = ((1.0 - v) * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, 1.0 - v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    pub fn random(&self, rng: &mut PRNG) -> Vec3 {
+        let ((u, row), _): ((f64, f64), f64) = self.distribution.sample(rng.gen::<f64>(), rng.gen::<f64>());
+        uv_to_direction(u, 1.0 - row, self.rotation)
+    }
+}
+
+// What rays that leave the scene see
+#[derive(Debug, Clone)]
+pub enum Background {
+    Constant(Color),
+    // The book's sky: blends from `bottom` straight down to `top` straight up
+    Gradient { bottom: Color, top: Color },
+    Environment(EnvironmentMap),
+    Sky(Box<Sky>),
+}
+
+impl Background {
+    pub fn new_constant(color: &Color) -> Self {
+        Self::Constant(*color)
+    }
+
+    pub fn new_gradient(bottom: &Color, top: &Color) -> Self {
+        Self::Gradient { bottom: *bottom, top: *top }
+    }
+
+    // `rotation` turns the map around +y, in degrees
+    pub fn new_environment<P: AsRef<Path>>(path: P, rotation: f64, intensity: f64) -> Result<Self, ImageError> {
+        Ok(Self::Environment(EnvironmentMap::load(path, rotation, intensity)?))
+    }
+
+    // Sun `elevation` and `azimuth` in degrees, see `Sky::new`
+    pub fn new_sky(elevation: f64, azimuth: f64, turbidity: f64, intensity: f64) -> Self {
+        Self::Sky(Box::new(Sky::new(elevation, azimuth, turbidity, intensity)))
+    }
+
+    // Radiance arriving from `direction`
+    pub fn value(&self, direction: &Vec3) -> Color {
+        match self {
+            Self::Constant(color) => *color,
+            Self::Gradient { bottom, top } => {
+                let t: f64 = 0.5 * (direction.normalize().y + 1.0);
+                *bottom * (1.0 - t) + *top * t
+            }
+            Self::Environment(map) => map.value(direction),
+            Self::Sky(sky) => sky.value(direction),
+        }
+    }
+
+    // Whether direct lighting samples the background like the other lights
+    pub fn is_light(&self) -> bool {
+        matches!(self, Self::Environment(_) | Self::Sky(_))
+    }
+
+    pub fn pdf_value(&self, direction: &Vec3) -> f64 {
+        match self {
+            Self::Constant(_) | Self::Gradient { .. } => 0.0,
+            Self::Environment(map) => map.pdf_value(direction),
+            Self::Sky(sky) => sky.pdf_value(direction),
+        }
+    }
+
+    pub fn random(&self, rng: &mut PRNG) -> Vec3 {
+        match self {
+            Self::Constant(_) | Self::Gradient { .. } => Vec3::random_unit_vector(rng),
+            Self::Environment(map) => map.random(rng),
+            Self::Sky(sky) => sky.random(rng),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use crate::vec3::{Vec3, Color};
+use crate::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+use crate::material::{Material, ScatterRecord};
+use crate::onb::Onb;
+use crate::PRNG;
+
+// Distance stepped along the surface for the finite differences of bump maps
+const BUMP_DELTA: f64 = 1e-3;
+
+// How the shading normal is perturbed
+#[derive(Debug, Clone)]
+pub enum Perturbation {
+    // Tangent space normals encoded as rgb = (n + 1) / 2, blue along the surface
+    // normal and green along +v. `strength` scales the tilt.
+    NormalMap { map: Texture, strength: f64 },
+    // Height along the normal read from the red channel, times `scale`
+    BumpMap { height: Texture, scale: f64 },
+}
+
+// Any material shaded with a perturbed normal. The geometric normal in the hit
+// record is left alone for the integrator, only the base material sees the new one.
+#[derive(Debug, Clone)]
+pub struct Bumped {
+    pub base: Arc<Material>,
+    pub perturbation: Perturbation,
+}
+
+impl Bumped {
+    pub fn new(base: &Material, perturbation: Perturbation) -> Self {
+        Self { base: Arc::new(base.clone()), perturbation }
+    }
+
+    // Tangent frame at the hit, falling back to an arbitrary one where the
+    // parameterization degenerates (e.g. the poles of a sphere)
+    fn tangents(rec: &HitRecord) -> (Vec3, Vec3) {
+        if rec.dpdu.near_zero() || rec.dpdv.near_zero() || rec.dpdu.cross(&rec.dpdv).near_zero() {
+            let uvw: Onb = Onb::build_from_w(&rec.normal);
+            return (uvw.u, uvw.v);
+        }
+        (rec.dpdu, rec.dpdv)
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let n: Vec3 = rec.normal;
+        let (dpdu, dpdv): (Vec3, Vec3) = Self::tangents(rec);
+        match &self.perturbation {
+            Perturbation::NormalMap { map, strength } => {
+                let c: Color = map.sample(rec);
+                let local: Vec3 = Vec3::new((2.0 * c.x - 1.0) * strength, (2.0 * c.y - 1.0) * strength, 2.0 * c.z - 1.0);
+                let t: Vec3 = (dpdu - n * n.dot(&dpdu)).normalize();
+                let mut b: Vec3 = n.cross(&t);
+                if b.dot(&dpdv) < 0.0 {
+                    b = -b;
+                }
+                (t * local.x + b * local.y + n * local.z).normalize()
+            }
+            Perturbation::BumpMap { height, scale } => {
+                // Height with the hit moved by `du`, `dv`. The object space point is moved
+                // by the same world offset, which is only exact for unrotated objects.
+                let h = |du: f64, dv: f64| {
+                    let offset: Vec3 = dpdu * du + dpdv * dv;
+                    let moved: HitRecord = HitRecord { u: rec.u + du, v: rec.v + dv, p: rec.p + offset, object_p: rec.object_p + offset, ..*rec };
+                    height.sample(&moved).x * scale
+                };
+                let h0: f64 = h(0.0, 0.0);
+                let du: f64 = BUMP_DELTA / dpdu.len();
+                let dv: f64 = BUMP_DELTA / dpdv.len();
+                let dhdu: f64 = (h(du, 0.0) - h0) / du;
+                let dhdv: f64 = (h(0.0, dv) - h0) / dv;
+                let bumped: Vec3 = (dpdu + n * dhdu).cross(&(dpdv + n * dhdv)).normalize();
+                if bumped.dot(&n) < 0.0 { -bumped } else { bumped }
+            }
+        }
+    }
+
+    // Hit record with the shading normal, unless it would face away from the viewer
+    fn shade<'mat>(&self, r: &Ray, rec: &HitRecord<'mat>) -> HitRecord<'mat> {
+        let normal: Vec3 = self.shading_normal(rec);
+        if normal.dot(&r.direction) >= 0.0 || normal.dot(&rec.normal) <= 0.0 {
+            return *rec;
+        }
+        HitRecord { normal, ..*rec }
+    }
+
+    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<ScatterRecord> {
+        self.base.scatter(r, &self.shade(r, rec), rng)
+    }
+
+    pub fn eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.base.eval(r, &self.shade(r, rec), direction)
+    }
+
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.base.pdf(r, &self.shade(r, rec), direction)
+    }
+
+    pub fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r, rec)
+    }
+}
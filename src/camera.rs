@@ -1,7 +1,7 @@
 use rand::prelude::Rng;
 
 use crate::vec3::{Vec3, Point3};
-use crate::ray::Ray;
+use crate::ray::{Ray, RayDifferential};
 use crate::PRNG;
 
 #[derive(Debug, Copy, Clone)]
@@ -61,4 +61,15 @@ impl Camera {
                  &(self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin - offset),
                  rng.gen_range(self.time0..self.time1))
     }
+
+    // Same as `get_ray`, with differentials towards the rays `ds` and `dt` away,
+    // usually one pixel, through the same point of the lens
+    pub fn get_ray_differential(&self, s: f64, t: f64, ds: f64, dt: f64, rng: &mut PRNG) -> Ray {
+        let r: Ray = self.get_ray(s, t, rng);
+        let differential: RayDifferential = RayDifferential { rx_origin: r.origin,
+                                                              rx_direction: r.direction + self.horizontal * ds,
+                                                              ry_origin: r.origin,
+                                                              ry_direction: r.direction + self.vertical * dt };
+        Ray { differential: Some(differential), ..r }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,76 @@
+use crate::vec3::{Vec3, Point3};
+use crate::hash_to_unit;
+
+// Which quantity of the Worley noise a texture shows
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CellularFeature {
+    // Distance to the nearest feature point (F1)
+    Distance,
+    // F2 - F1, zero on the borders between cells
+    Edge,
+    // A random gray level per cell, e.g. to vary stones or tiles
+    Cell,
+}
+
+// Worley / Voronoi cellular noise: one random feature point per unit cell.
+// The points are hashed from the cell and the seed, so nothing is stored.
+#[derive(Debug, Copy, Clone)]
+pub struct Worley {
+    seed: u64,
+}
+
+fn hash(cell: (i64, i64, i64), seed: u64, channel: u64) -> f64 {
+    let mut x: u64 = seed ^ channel.wrapping_mul(0x9e3779b97f4a7c15);
+    for c in [cell.0, cell.1, cell.2] {
+        x = (x ^ c as u64).wrapping_mul(0xbf58476d1ce4e5b9);
+        x ^= x >> 31;
+    }
+    hash_to_unit(x)
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    fn feature_point(&self, cell: (i64, i64, i64)) -> Point3 {
+        Point3::new(cell.0 as f64 + hash(cell, self.seed, 0),
+                    cell.1 as f64 + hash(cell, self.seed, 1),
+                    cell.2 as f64 + hash(cell, self.seed, 2))
+    }
+
+    // Distances to the two nearest feature points and the cell of the nearest
+    fn nearest(&self, p: &Point3) -> (f64, f64, (i64, i64, i64)) {
+        let base: (i64, i64, i64) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        let mut f1: f64 = f64::INFINITY;
+        let mut f2: f64 = f64::INFINITY;
+        let mut nearest: (i64, i64, i64) = base;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let cell: (i64, i64, i64) = (base.0 + dx, base.1 + dy, base.2 + dz);
+                    let offset: Vec3 = self.feature_point(cell) - *p;
+                    let distance: f64 = offset.len();
+                    if distance < f1 {
+                        f2 = f1;
+                        f1 = distance;
+                        nearest = cell;
+                    } else if distance < f2 {
+                        f2 = distance;
+                    }
+                }
+            }
+        }
+        (f1, f2, nearest)
+    }
+
+    // Feature at `p`, roughly in [0, 1]
+    pub fn value(&self, p: &Point3, feature: CellularFeature) -> f64 {
+        let (f1, f2, cell): (f64, f64, (i64, i64, i64)) = self.nearest(p);
+        match feature {
+            CellularFeature::Distance => f1.min(1.0),
+            CellularFeature::Edge => (f2 - f1).min(1.0),
+            CellularFeature::Cell => hash(cell, self.seed, 3),
+        }
+    }
+}
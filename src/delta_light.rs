@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use crate::ies::IesProfile;
+use crate::onb::Onb;
+use crate::vec3::{Vec3, Point3, Color};
+
+// Lights without any area: they can't be hit by rays, only reached with shadow rays
+#[derive(Debug, Clone)]
+pub enum DeltaLight {
+    // Radiant intensity `intensity` in every direction
+    Point { position: Point3, intensity: Color },
+    // Point light limited to a cone around `direction`, fading out between the cosines
+    Spot { position: Point3, direction: Vec3, intensity: Color, cos_falloff_start: f64, cos_total_width: f64 },
+    // Light travelling along `direction` from infinitely far away, such as the sun
+    Directional { direction: Vec3, irradiance: Color },
+    // Point light shaped by a measured IES profile, `frame.w` is the luminaire's nadir.
+    // `intensity` is reached where the profile is brightest.
+    Photometric { position: Point3, frame: Onb, intensity: Color, profile: Arc<IesProfile> },
+}
+
+// Incoming light at a point: unit direction towards the light, its distance and the radiance
+// it delivers (already divided by the squared distance)
+pub struct LightSample {
+    pub direction: Vec3,
+    pub distance: f64,
+    pub radiance: Color,
+}
+
+fn smoothstep(x: f64, a: f64, b: f64) -> f64 {
+    if a == b {
+        return if x < a { 0.0 } else { 1.0 };
+    }
+    let t: f64 = ((x - a) / (b - a)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl DeltaLight {
+    pub fn new_point(position: &Point3, intensity: &Color) -> Self {
+        Self::Point { position: *position, intensity: *intensity }
+    }
+
+    // Spot at `position` pointed at `target`. `total_width` is the half angle of the cone in degrees,
+    // the light starts fading at `falloff_start` degrees from the axis.
+    pub fn new_spot(position: &Point3, target: &Point3, intensity: &Color, total_width: f64, falloff_start: f64) -> Self {
+        Self::Spot { position: *position,
+                     direction: (*target - *position).normalize(),
+                     intensity: *intensity,
+                     cos_falloff_start: falloff_start.min(total_width).to_radians().cos(),
+                     cos_total_width: total_width.to_radians().cos() }
+    }
+
+    pub fn new_directional(direction: &Vec3, irradiance: &Color) -> Self {
+        Self::Directional { direction: direction.normalize(), irradiance: *irradiance }
+    }
+
+    // Luminaire at `position` with its nadir pointed at `target`, turned by `rotation` degrees
+    // around that axis. `profile.max_candela()` is the measured peak, if `intensity` should match it.
+    pub fn new_photometric(position: &Point3, target: &Point3, rotation: f64, intensity: &Color, profile: &IesProfile) -> Self {
+        let axis: Onb = Onb::build_from_w(&(*target - *position));
+        let (sin, cos): (f64, f64) = rotation.to_radians().sin_cos();
+        let frame: Onb = Onb { u: axis.u * cos + axis.v * sin, v: axis.v * cos - axis.u * sin, w: axis.w };
+        Self::Photometric { position: *position,
+                            frame,
+                            intensity: *intensity,
+                            profile: Arc::new(profile.clone()) }
+    }
+
+    pub fn sample(&self, p: &Point3) -> LightSample {
+        match self {
+            Self::Point { position, intensity } => {
+                let to_light: Vec3 = *position - *p;
+                let distance: f64 = to_light.len();
+                LightSample { direction: to_light / distance, distance, radiance: *intensity / (distance * distance) }
+            }
+            Self::Spot { position, direction, intensity, cos_falloff_start, cos_total_width } => {
+                let to_light: Vec3 = *position - *p;
+                let distance: f64 = to_light.len();
+                let cos_theta: f64 = -(to_light / distance).dot(direction);
+                let falloff: f64 = smoothstep(cos_theta, *cos_total_width, *cos_falloff_start);
+                LightSample { direction: to_light / distance, distance, radiance: *intensity * (falloff / (distance * distance)) }
+            }
+            Self::Directional { direction, irradiance } => {
+                LightSample { direction: -*direction, distance: f64::INFINITY, radiance: *irradiance }
+            }
+            Self::Photometric { position, frame, intensity, profile } => {
+                let to_light: Vec3 = *position - *p;
+                let distance: f64 = to_light.len();
+                let shape: f64 = profile.value_local(&frame.to_local(&-to_light));
+                LightSample { direction: to_light / distance, distance, radiance: *intensity * (shape / (distance * distance)) }
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// Piecewise constant distributions for importance sampling tabulated functions
+// (environment maps, light powers), as in pbrt's Distribution1D/2D
+
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    // `func` must not be negative. An all zero function is sampled uniformly.
+    pub fn new(func: &[f64]) -> Self {
+        let n: usize = func.len();
+        let mut cdf: Vec<f64> = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f64;
+        }
+        let integral: f64 = cdf[n];
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if integral > 0.0 { *value / integral } else { i as f64 / n as f64 };
+        }
+        Self { func: func.to_vec(), cdf, integral }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    // Point in [0, 1) for the uniform `u`, with its density and the bucket it fell in
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let offset: usize = self.cdf.partition_point(|value| *value <= u).clamp(1, self.count()) - 1;
+        let width: f64 = self.cdf[offset + 1] - self.cdf[offset];
+        let du: f64 = if width > 0.0 { (u - self.cdf[offset]) / width } else { 0.0 };
+        let x: f64 = ((offset as f64 + du) / self.count() as f64).min(1.0 - 1e-12);
+        (x, self.pdf(x), offset)
+    }
+
+    // Bucket for the uniform `u`, with its probability
+    pub fn sample_discrete(&self, u: f64) -> (usize, f64) {
+        let offset: usize = self.cdf.partition_point(|value| *value <= u).clamp(1, self.count()) - 1;
+        (offset, self.discrete_pdf(offset))
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[index] / (self.integral * self.count() as f64)
+        } else {
+            1.0 / self.count() as f64
+        }
+    }
+
+    // Density at `x` in [0, 1)
+    pub fn pdf(&self, x: f64) -> f64 {
+        let index: usize = ((x * self.count() as f64) as usize).min(self.count() - 1);
+        self.discrete_pdf(index) * self.count() as f64
+    }
+}
+
+// Function over [0, 1)^2 sampled by a marginal over rows and a conditional per row
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // `func` is row major, `width` values per row
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        let conditional: Vec<Distribution1D> = (0..height).map(|y| Distribution1D::new(&func[y * width..(y + 1) * width])).collect();
+        let row_integrals: Vec<f64> = conditional.iter().map(|row| row.integral()).collect();
+        Self { conditional, marginal: Distribution1D::new(&row_integrals) }
+    }
+
+    // (x, y) in [0, 1)^2 and its density
+    pub fn sample(&self, u1: f64, u2: f64) -> ((f64, f64), f64) {
+        let (y, pdf_y, row): (f64, f64, usize) = self.marginal.sample_continuous(u2);
+        let (x, pdf_x, _): (f64, f64, usize) = self.conditional[row].sample_continuous(u1);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, x: f64, y: f64) -> f64 {
+        let row: usize = ((y * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        self.marginal.pdf(y) * self.conditional[row].pdf(x)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::vec3::{Point3};
+use crate::vec3::{Vec3, Point3};
 use crate::hittable::{HitRecord, Hittable};
 use crate::hittable_list::HittableList;
 use crate::ray::Ray;
@@ -30,6 +30,19 @@ impl HBox {
 
 impl Hittable for HBox {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        self.sides.hit(r, t_min, t_max)
+        let mut rec: HitRecord = self.sides.hit(r, t_min, t_max)?;
+
+        // The sides are one-sided rects with normals along +x/+y/+z, so the min
+        // faces point inwards. Recompute front_face against the outward normal
+        // so that refractive and subsurface materials know when they are entered.
+        let eps: f64 = 1e-6 * (self.max - self.min).len();
+        let outward_normal: Vec3 = if (rec.p.x - self.min.x).abs() < eps { Vec3::new(-1.0, 0.0, 0.0) }
+            else if (rec.p.x - self.max.x).abs() < eps { Vec3::new(1.0, 0.0, 0.0) }
+            else if (rec.p.y - self.min.y).abs() < eps { Vec3::new(0.0, -1.0, 0.0) }
+            else if (rec.p.y - self.max.y).abs() < eps { Vec3::new(0.0, 1.0, 0.0) }
+            else if (rec.p.z - self.min.z).abs() < eps { Vec3::new(0.0, 0.0, -1.0) }
+            else { Vec3::new(0.0, 0.0, 1.0) };
+        rec.set_face_normal(*r, outward_normal);
+        Some(rec)
     }
 }
\ No newline at end of file
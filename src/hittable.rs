@@ -1,6 +1,11 @@
 use crate::ray::Ray;
 use crate::vec3::{Vec3, Point3};
 use crate::material::Material;
+use crate::texture::Footprint;
+use crate::aabb::Aabb;
+use crate::light_tree::LightBounds;
+use crate::PRNG;
+use crate::hash_to_unit;
 
 #[derive(Debug, Copy, Clone)]
 pub struct HitRecord<'mat> {
@@ -8,7 +13,17 @@ pub struct HitRecord<'mat> {
     pub u: f64,
     pub v: f64,
     pub p: Point3,
+    // Hit point in the object's own frame, before any Translate or RotateY
+    pub object_p: Point3,
     pub normal: Vec3,
+    // Partial derivatives of p along u and v, the tangent frame for normal mapping
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    // Partial derivatives of the normal, to follow ray differentials off curved mirrors
+    pub dndu: Vec3,
+    pub dndv: Vec3,
+    // Texture filter footprint, filled in by the integrator from the ray differentials
+    pub footprint: Footprint,
     pub front_face: bool,
     pub material: &'mat Material,
 }
@@ -18,10 +33,77 @@ impl<'mat> HitRecord<'mat> {
 	    self.front_face = Vec3::dot(&r.direction, &outward_normal) < 0.0;
         self.normal = if self.front_face { outward_normal } else { -outward_normal }
     }
+
+    // Where the ray's differentials cross the tangent plane, and the matching
+    // change in (u, v) by least squares over dpdu and dpdv (as in pbrt)
+    pub fn compute_footprint(&mut self, r: &Ray) {
+        let differential = match r.differential {
+            Some(differential) => differential,
+            None => return,
+        };
+        let d: f64 = self.normal.dot(&self.p);
+        let tx: f64 = (d - self.normal.dot(&differential.rx_origin)) / self.normal.dot(&differential.rx_direction);
+        let ty: f64 = (d - self.normal.dot(&differential.ry_origin)) / self.normal.dot(&differential.ry_direction);
+        if !tx.is_finite() || !ty.is_finite() {
+            return;
+        }
+        let dpdx: Vec3 = differential.rx_origin + differential.rx_direction * tx - self.p;
+        let dpdy: Vec3 = differential.ry_origin + differential.ry_direction * ty - self.p;
+
+        let ata00: f64 = self.dpdu.dot(&self.dpdu);
+        let ata01: f64 = self.dpdu.dot(&self.dpdv);
+        let ata11: f64 = self.dpdv.dot(&self.dpdv);
+        let inv_det: f64 = 1.0 / (ata00 * ata11 - ata01 * ata01);
+        let inv_det: f64 = if inv_det.is_finite() { inv_det } else { 0.0 };
+        let solve = |dp: &Vec3| -> (f64, f64) {
+            let atb0: f64 = self.dpdu.dot(dp);
+            let atb1: f64 = self.dpdv.dot(dp);
+            let du: f64 = (ata11 * atb0 - ata01 * atb1) * inv_det;
+            let dv: f64 = (ata00 * atb1 - ata01 * atb0) * inv_det;
+            (du.clamp(-1e8, 1e8), dv.clamp(-1e8, 1e8))
+        };
+        let (dudx, dvdx): (f64, f64) = solve(&dpdx);
+        let (dudy, dvdy): (f64, f64) = solve(&dpdy);
+        self.footprint = Footprint { dudx, dvdx, dudy, dvdy, dpdx, dpdy };
+    }
+
+    // Whether the material's opacity mask removes this hit. Fractional opacity uses hashed
+    // alpha testing (Wyman and McGuire 2017): the hash only depends on the surface
+    // coordinates, so a point is either solid or not for every ray and shadows match
+    // what the camera sees.
+    pub fn is_cut_out(&self) -> bool {
+        let opacity: f64 = self.material.opacity(self);
+        if opacity >= 1.0 {
+            return false;
+        }
+        if opacity <= 0.0 {
+            return true;
+        }
+        hash_uv(self.u, self.v) >= opacity
+    }
+}
+
+// Uniform value in [0, 1) from the bits of `u` and `v`
+fn hash_uv(u: f64, v: f64) -> f64 {
+    hash_to_unit(u.to_bits() ^ v.to_bits().rotate_left(32))
 }
 
 pub trait Hittable: Sync + Send {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    // Solid angle pdf of sampling `direction` from `origin` with `random`, for shapes usable as lights
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn random(&self, _origin: &Point3, _rng: &mut PRNG) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    // Bounds and power for choosing between lights
+    fn light_bounds(&self) -> Option<LightBounds> {
+        None
+    }
 }
 
 pub struct Translate {
@@ -40,22 +122,42 @@ impl Hittable for Translate {
         let moved_r: Ray = Ray::new(&(r.origin - self.offset), &r.direction, r.time);
         match self.object.hit(&moved_r, t_min, t_max) {
             Some(rec) => {
-                let front_face: bool = moved_r.direction.dot(&rec.normal) < 0.0;
                 let p = rec.p + self.offset;
 
+                // The normal already faces the ray, keep the object's own idea of front_face
                 Some(HitRecord {
                     t: rec.t,
                     u: rec.u,
                     v: rec.v,
                     p: p,
-                    normal: if front_face { rec.normal } else { -rec.normal },
-                    front_face: front_face,
+                    object_p: rec.object_p,
+                    normal: rec.normal,
+                    dpdu: rec.dpdu,
+                    dpdv: rec.dpdv,
+                    dndu: rec.dndu,
+                    dndv: rec.dndv,
+                    footprint: rec.footprint,
+                    front_face: rec.front_face,
                     material: rec.material,
                 })
             }
             None => None
         }
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(&(*origin - self.offset), direction)
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        self.object.random(&(*origin - self.offset), rng)
+    }
+
+    fn light_bounds(&self) -> Option<LightBounds> {
+        self.object.light_bounds().map(|light| {
+            LightBounds { bounds: Aabb::new(&(light.bounds.minimum + self.offset), &(light.bounds.maximum + self.offset)), ..light }
+        })
+    }
 }
 
 pub struct RotateY {
@@ -70,6 +172,16 @@ impl RotateY {
 
         Self { object: object, sin_theta: radians.sin(), cos_theta: radians.cos() }
     }
+
+    // World -> object space
+    fn to_object(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(self.cos_theta * a.x - self.sin_theta * a.z, a.y, self.sin_theta * a.x + self.cos_theta * a.z)
+    }
+
+    // Object -> world space
+    fn to_world(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(self.cos_theta * a.x + self.sin_theta * a.z, a.y, -self.sin_theta * a.x + self.cos_theta * a.z)
+    }
 }
 
 impl Hittable for RotateY {
@@ -90,19 +202,84 @@ impl Hittable for RotateY {
                 let normal: Point3 = Point3::new(self.cos_theta * rec.normal.x + self.sin_theta * rec.normal.z,
                                                  rec.normal.y,
                                                  -self.sin_theta * rec.normal.x + self.cos_theta * rec.normal.z);
-                let front_face: bool = rotated_r.direction.dot(&normal) < 0.0;
+                let dpdu: Vec3 = Vec3::new(self.cos_theta * rec.dpdu.x + self.sin_theta * rec.dpdu.z,
+                                           rec.dpdu.y,
+                                           -self.sin_theta * rec.dpdu.x + self.cos_theta * rec.dpdu.z);
+                let dpdv: Vec3 = Vec3::new(self.cos_theta * rec.dpdv.x + self.sin_theta * rec.dpdv.z,
+                                           rec.dpdv.y,
+                                           -self.sin_theta * rec.dpdv.x + self.cos_theta * rec.dpdv.z);
+                let dndu: Vec3 = self.to_world(&rec.dndu);
+                let dndv: Vec3 = self.to_world(&rec.dndv);
 
                 Some(HitRecord {
                     t: rec.t,
                     u: rec.u,
                     v: rec.v,
                     p: p,
-                    normal: if front_face { normal } else { -normal },
-                    front_face: front_face,
+                    object_p: rec.object_p,
+                    normal,
+                    dpdu,
+                    dpdv,
+                    dndu,
+                    dndv,
+                    footprint: rec.footprint,
+                    front_face: rec.front_face,
                     material: rec.material,
                 })
             }
             None => None
         }
     }
-}
\ No newline at end of file
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(&self.to_object(origin), &self.to_object(direction))
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        self.to_world(&self.object.random(&self.to_object(origin), rng))
+    }
+
+    fn light_bounds(&self) -> Option<LightBounds> {
+        self.object.light_bounds().map(|light| {
+            if !light.bounds.is_finite() {
+                return light;
+            }
+            let corners: [Point3; 8] = light.bounds.corners();
+            let first: Point3 = self.to_world(&corners[0]);
+            let bounds: Aabb = corners.iter().skip(1).fold(Aabb::new(&first, &first), |acc, corner| {
+                let corner: Point3 = self.to_world(corner);
+                acc.surrounding_box(&Aabb::new(&corner, &corner))
+            });
+            LightBounds { bounds, ..light }
+        })
+    }
+}
+
+// Turns an object inside out, e.g. to point a one-sided light the other way
+pub struct FlipFace {
+    object: Box<dyn Hittable>,
+}
+
+impl FlipFace {
+    pub fn new(object: Box<dyn Hittable>) -> Self {
+        Self { object }
+    }
+}
+
+impl Hittable for FlipFace {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.object.hit(r, t_min, t_max).map(|rec| HitRecord { front_face: !rec.front_face, ..rec })
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        self.object.random(origin, rng)
+    }
+
+    fn light_bounds(&self) -> Option<LightBounds> {
+        self.object.light_bounds()
+    }
+}
@@ -1,5 +1,10 @@
+use rand::Rng;
+
 use crate::ray::Ray;
 use crate::hittable::*;
+use crate::light_tree::LightBounds;
+use crate::vec3::{Vec3, Point3};
+use crate::PRNG;
 
 
 pub struct HittableList {
@@ -18,14 +23,43 @@ impl Hittable for HittableList {
 		let mut hit_record = None;
 
 		for object in self.objects.iter(){
-			match object.hit(r, t_min, closest_so_far) {
-				Some(rec) => {
+			// Look past hits removed by an opacity mask
+			let mut t_start: f64 = t_min;
+			while let Some(rec) = object.hit(r, t_start, closest_so_far) {
+				if !rec.is_cut_out() {
 					closest_so_far = rec.t;
 					hit_record = Some(rec);
+					break;
 				}
-				None => {}
+				t_start = rec.t + 1e-9;
 			}
 		}
 		hit_record
 	}
+
+	// Uniform mixture over the objects
+	fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+		if self.objects.is_empty() {
+			return 0.0;
+		}
+		let weight: f64 = 1.0 / (self.objects.len() as f64);
+		self.objects.iter().map(|object| weight * object.pdf_value(origin, direction)).sum()
+	}
+
+	fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+		if self.objects.is_empty() {
+			return Vec3::new(1.0, 0.0, 0.0);
+		}
+		let index: usize = rng.gen_range(0..self.objects.len());
+		self.objects[index].random(origin, rng)
+	}
+
+	// Union of the objects' bounds and their total power, None if any of them has none
+	fn light_bounds(&self) -> Option<LightBounds> {
+		let mut objects = self.objects.iter();
+		let first: LightBounds = objects.next()?.light_bounds()?;
+		objects.try_fold(first, |acc, object| {
+			object.light_bounds().map(|light| LightBounds { bounds: acc.bounds.surrounding_box(&light.bounds), power: acc.power + light.power })
+		})
+	}
 }
@@ -0,0 +1,210 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum IesError {
+    Io(std::io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for IesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "error reading IES file: {}", error),
+            Self::Parse(message) => write!(f, "error parsing IES file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for IesError {}
+
+impl From<std::io::Error> for IesError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+// Index of the segment of the sorted `angles` holding `angle` and the position inside it,
+// None outside the measured range
+fn locate(angles: &[f64], angle: f64) -> Option<(usize, f64)> {
+    if angles.len() == 1 {
+        return Some((0, 0.0));
+    }
+    let (first, last): (f64, f64) = (angles[0], angles[angles.len() - 1]);
+    if angle < first - 1e-9 || angle > last + 1e-9 {
+        return None;
+    }
+    let angle: f64 = angle.clamp(first, last);
+    let i: usize = angles.partition_point(|a| *a <= angle).clamp(1, angles.len() - 1) - 1;
+    let width: f64 = angles[i + 1] - angles[i];
+    Some((i, if width > 0.0 { (angle - angles[i]) / width } else { 0.0 }))
+}
+
+// Luminous intensity distribution from an IESNA LM-63 photometric file (type C, the usual
+// kind for architectural luminaires). Vertical angles go from 0 at the nadir, where the
+// luminaire points, to 180 straight up; horizontal angles turn around the nadir.
+#[derive(Debug, Clone)]
+pub struct IesProfile {
+    vertical_angles: Vec<f64>,
+    horizontal_angles: Vec<f64>,
+    // One row of vertical samples per horizontal angle, divided by the maximum
+    candela: Vec<f64>,
+    // Brightest intensity in the file, in candela
+    max_candela: f64,
+}
+
+impl IesProfile {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, IesError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, IesError> {
+        let mut lines = text.lines();
+        // Keywords and comments up to the TILT line
+        let tilt: &str = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => break line.trim_start()["TILT=".len()..].trim(),
+                Some(_) => continue,
+                None => return Err(IesError::Parse("missing TILT line".to_string())),
+            }
+        };
+        let rest: String = lines.collect::<Vec<&str>>().join(" ");
+        let mut numbers = rest.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()).map(|token| {
+            token.parse::<f64>().map_err(|_| IesError::Parse(format!("not a number: {}", token)))
+        });
+        let mut next = || -> Result<f64, IesError> {
+            numbers.next().unwrap_or_else(|| Err(IesError::Parse("unexpected end of file".to_string())))
+        };
+
+        match tilt {
+            "NONE" => {}
+            "INCLUDE" => {
+                // Lamp tilt table, only matters for lamps mounted at an angle: skipped
+                let _geometry: f64 = next()?;
+                let count: usize = next()? as usize;
+                for _ in 0..2 * count {
+                    next()?;
+                }
+            }
+            _ => return Err(IesError::Parse(format!("tilt data in a separate file ({}) is not supported", tilt))),
+        }
+
+        let _lamps: f64 = next()?;
+        let _lumens_per_lamp: f64 = next()?;
+        let multiplier: f64 = next()?;
+        let vertical_count: usize = next()? as usize;
+        let horizontal_count: usize = next()? as usize;
+        let photometric_type: f64 = next()?;
+        let _units: f64 = next()?;
+        let (_width, _length, _height): (f64, f64, f64) = (next()?, next()?, next()?);
+        let ballast_factor: f64 = next()?;
+        let _ballast_lamp_factor: f64 = next()?;
+        let _input_watts: f64 = next()?;
+        if photometric_type != 1.0 {
+            return Err(IesError::Parse("only type C photometry is supported".to_string()));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(IesError::Parse("no angles".to_string()));
+        }
+
+        let vertical_angles: Vec<f64> = (0..vertical_count).map(|_| next()).collect::<Result<_, _>>()?;
+        let horizontal_angles: Vec<f64> = (0..horizontal_count).map(|_| next()).collect::<Result<_, _>>()?;
+        let mut candela: Vec<f64> = (0..vertical_count * horizontal_count).map(|_| next().map(|value| value * multiplier * ballast_factor)).collect::<Result<_, _>>()?;
+        let max_candela: f64 = candela.iter().cloned().fold(0.0, f64::max);
+        if max_candela > 0.0 {
+            candela.iter_mut().for_each(|value| *value /= max_candela);
+        }
+        Ok(Self { vertical_angles, horizontal_angles, candela, max_candela })
+    }
+
+    pub fn max_candela(&self) -> f64 {
+        self.max_candela
+    }
+
+    // Horizontal angle folded into the range the file covers, using its symmetry
+    fn fold_horizontal(&self, phi: f64) -> f64 {
+        let last: f64 = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        let mut phi: f64 = phi.rem_euclid(360.0);
+        if last <= 180.0 && phi > 180.0 {
+            phi = 360.0 - phi;
+        }
+        if last <= 90.0 && phi > 90.0 {
+            phi = 180.0 - phi;
+        }
+        phi
+    }
+
+    // Relative intensity in [0, 1], `theta` degrees from the nadir and `phi` degrees around it
+    pub fn value(&self, theta: f64, phi: f64) -> f64 {
+        let (v, tv): (usize, f64) = match locate(&self.vertical_angles, theta) {
+            Some(location) => location,
+            None => return 0.0,
+        };
+        let (h, th): (usize, f64) = locate(&self.horizontal_angles, self.fold_horizontal(phi)).unwrap_or((self.horizontal_angles.len() - 1, 0.0));
+        let vertical_count: usize = self.vertical_angles.len();
+        let sample = |h: usize, v: usize| -> f64 {
+            self.candela[h.min(self.horizontal_angles.len() - 1) * vertical_count + v.min(vertical_count - 1)]
+        };
+        let near: f64 = sample(h, v) * (1.0 - tv) + sample(h, v + 1) * tv;
+        let far: f64 = sample(h + 1, v) * (1.0 - tv) + sample(h + 1, v + 1) * tv;
+        near * (1.0 - th) + far * th
+    }
+
+    // Same for a direction in the luminaire's frame, +z at the nadir and +x at phi = 0
+    pub fn value_local(&self, direction: &Vec3) -> f64 {
+        let d: Vec3 = direction.normalize();
+        let theta: f64 = d.z.clamp(-1.0, 1.0).acos().to_degrees();
+        let phi: f64 = d.y.atan2(d.x).to_degrees();
+        self.value(theta, phi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Quadrant symmetric profile: 3 vertical angles by 2 horizontal ones, twice as bright at 90
+    const LAMP: &str = "1 1000 1 3 2 1 1 0.5 0.5 0 1 1 100\n0 45 90\n0 90\n100 50 0\n200 100 0\n";
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn parse_without_tilt() {
+        let profile: IesProfile = IesProfile::parse(&format!("IESNA:LM-63-2002\n[TEST] quadrant\nTILT=NONE\n{}", LAMP)).unwrap();
+        assert!(close(profile.max_candela(), 200.0));
+        assert!(close(profile.value(0.0, 0.0), 0.5));
+        assert!(close(profile.value(0.0, 90.0), 1.0));
+        assert!(close(profile.value(45.0, 45.0), 0.375));
+    }
+
+    #[test]
+    fn parse_with_included_tilt() {
+        let profile: IesProfile = IesProfile::parse(&format!("IESNA:LM-63-2002\nTILT=INCLUDE\n1\n2\n0 90\n1 1\n{}", LAMP)).unwrap();
+        assert!(close(profile.max_candela(), 200.0));
+        assert!(close(profile.value(45.0, 45.0), 0.375));
+    }
+
+    #[test]
+    fn tilt_file_is_rejected() {
+        assert!(matches!(IesProfile::parse(&format!("TILT=lamp.tilt\n{}", LAMP)), Err(IesError::Parse(_))));
+    }
+
+    #[test]
+    fn horizontal_symmetry_folds() {
+        let profile: IesProfile = IesProfile::parse(&format!("TILT=NONE\n{}", LAMP)).unwrap();
+        assert!(close(profile.value(45.0, 270.0), profile.value(45.0, 90.0)));
+        assert!(close(profile.value(30.0, 135.0), profile.value(30.0, 45.0)));
+        assert!(close(profile.value(30.0, -45.0), profile.value(30.0, 45.0)));
+    }
+
+    #[test]
+    fn outside_vertical_range_is_dark() {
+        let profile: IesProfile = IesProfile::parse(&format!("TILT=NONE\n{}", LAMP)).unwrap();
+        assert_eq!(profile.value(120.0, 0.0), 0.0);
+        assert_eq!(profile.value(180.0, 90.0), 0.0);
+    }
+}
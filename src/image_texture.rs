@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use image::ImageError;
+
+use crate::vec3::Color;
+
+// What happens to texture coordinates outside [0, 1]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+impl WrapMode {
+    fn wrap(&self, i: i64, size: usize) -> usize {
+        let size: i64 = size as i64;
+        let wrapped: i64 = match self {
+            Self::Repeat => i.rem_euclid(size),
+            Self::Mirror => {
+                let period: i64 = i.rem_euclid(2 * size);
+                if period < size { period } else { 2 * size - 1 - period }
+            }
+            Self::Clamp => i.clamp(0, size - 1),
+        };
+        wrapped as usize
+    }
+}
+
+// Exact sRGB transfer function, encoded -> linear
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+// One level of the mip pyramid, linear colors with row 0 at the top
+#[derive(Debug, Clone)]
+struct Level {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Level {
+    fn texel(&self, x: i64, y: i64, wrap: WrapMode) -> Color {
+        self.pixels[wrap.wrap(y, self.height) * self.width + wrap.wrap(x, self.width)]
+    }
+
+    // Bilinear lookup, v = 0 is the bottom of the image
+    fn bilinear(&self, u: f64, v: f64, wrap: WrapMode) -> Color {
+        let x: f64 = u * self.width as f64 - 0.5;
+        let y: f64 = (1.0 - v) * self.height as f64 - 0.5;
+        let (x0, y0): (f64, f64) = (x.floor(), y.floor());
+        let (fx, fy): (f64, f64) = (x - x0, y - y0);
+        let (x0, y0): (i64, i64) = (x0 as i64, y0 as i64);
+
+        let top: Color = self.texel(x0, y0, wrap) * (1.0 - fx) + self.texel(x0 + 1, y0, wrap) * fx;
+        let bottom: Color = self.texel(x0, y0 + 1, wrap) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1, wrap) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    // Next level down, averaging 2x2 blocks (the last row or column is reused for odd sizes)
+    fn downsample(&self) -> Self {
+        let width: usize = (self.width / 2).max(1);
+        let height: usize = (self.height / 2).max(1);
+        let mut pixels: Vec<Color> = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0): (i64, i64) = (2 * x as i64, 2 * y as i64);
+                let sum: Color = self.texel(x0, y0, WrapMode::Clamp) + self.texel(x0 + 1, y0, WrapMode::Clamp)
+                    + self.texel(x0, y0 + 1, WrapMode::Clamp) + self.texel(x0 + 1, y0 + 1, WrapMode::Clamp);
+                pixels.push(sum * 0.25);
+            }
+        }
+        Self { width, height, pixels }
+    }
+}
+
+// Decoded image with its mip pyramid, level 0 is the full resolution
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    levels: Vec<Level>,
+}
+
+impl ImageTexture {
+    // Loads any format the `image` crate reads (PNG, JPEG, ...). Color images are
+    // sRGB encoded, data such as normal or roughness maps should pass `srgb: false`.
+    pub fn load<P: AsRef<Path>>(path: P, srgb: bool) -> Result<Self, ImageError> {
+        let image = image::open(path)?.to_rgb32f();
+        let decode = |value: f32| -> f64 { if srgb { srgb_to_linear(value as f64) } else { value as f64 } };
+        let pixels: Vec<Color> = image.pixels().map(|pixel| Color::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2]))).collect();
+        Ok(Self::from_pixels(image.width() as usize, image.height() as usize, pixels))
+    }
+
+    // Linear colors, row major with row 0 at the top
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        let mut levels: Vec<Level> = vec![Level { width, height, pixels }];
+        while levels.last().is_some_and(|level| !level.pixels.is_empty() && (level.width > 1 || level.height > 1)) {
+            let next: Level = levels[levels.len() - 1].downsample();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    // Size of the full resolution image
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.levels[0].width, self.levels[0].height)
+    }
+
+    // Texel of the full resolution image, row 0 at the top
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.levels[0].pixels[y * self.levels[0].width + x]
+    }
+
+    // Bilinear lookup in the full resolution image, v = 0 is the bottom
+    pub fn sample(&self, u: f64, v: f64, wrap: WrapMode) -> Color {
+        self.sample_filtered(u, v, wrap, 0.0)
+    }
+
+    // Trilinear lookup for a footprint `width` wide in (u, v)
+    pub fn sample_filtered(&self, u: f64, v: f64, wrap: WrapMode, width: f64) -> Color {
+        if self.levels[0].pixels.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+        let texels: f64 = width * self.levels[0].width.max(self.levels[0].height) as f64;
+        let level: f64 = texels.max(1.0).log2().min((self.levels.len() - 1) as f64);
+        let lower: usize = level.floor() as usize;
+        let upper: usize = (lower + 1).min(self.levels.len() - 1);
+        let t: f64 = level - lower as f64;
+        if t <= 0.0 {
+            return self.levels[lower].bilinear(u, v, wrap);
+        }
+        self.levels[lower].bilinear(u, v, wrap) * (1.0 - t) + self.levels[upper].bilinear(u, v, wrap) * t
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use rand::prelude::Rng;
+
+use crate::vec3::{Vec3, Color};
+use crate::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+use crate::material::{self, Material, ScatterRecord};
+use crate::medium::Medium;
+use crate::microfacet;
+use crate::onb::Onb;
+use crate::PRNG;
+
+// Blend of two materials, `weight` (red channel) selects `second`
+#[derive(Debug, Clone)]
+pub struct Mix {
+    pub first: Arc<Material>,
+    pub second: Arc<Material>,
+    pub weight: Texture,
+}
+
+// Smooth or rough dielectric coating over any material, e.g. varnish or car paint.
+// Light reaching the base is attenuated by the Fresnel transmittance of the coat
+// on the way in and out.
+#[derive(Debug, Clone)]
+pub struct Clearcoat {
+    pub base: Arc<Material>,
+    pub refraction_index: f64,
+    pub alpha: f64,
+}
+
+impl Mix {
+    pub fn new(first: &Material, second: &Material, weight: &Texture) -> Self {
+        Self { first: Arc::new(first.clone()), second: Arc::new(second.clone()), weight: weight.clone() }
+    }
+
+    fn weight(&self, rec: &HitRecord) -> f64 {
+        self.weight.sample(rec).x.clamp(0.0, 1.0)
+    }
+
+    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<ScatterRecord> {
+        let weight: f64 = self.weight(rec);
+        let chosen: &Material = if rng.gen::<f64>() < weight { &self.second } else { &self.first };
+        let srec: ScatterRecord = chosen.scatter(r, rec, rng)?;
+        if srec.pdf.is_none() {
+            // Specular: the selection probability cancels with the blend weight
+            return Some(srec);
+        }
+        let direction: Vec3 = srec.scattered.direction;
+        let pdf: f64 = self.pdf(r, rec, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord { attenuation: self.eval(r, rec, &direction) / pdf, pdf: Some(pdf), ..srec })
+    }
+
+    pub fn eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let weight: f64 = self.weight(rec);
+        self.first.eval(r, rec, direction) * (1.0 - weight) + self.second.eval(r, rec, direction) * weight
+    }
+
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let weight: f64 = self.weight(rec);
+        self.first.pdf(r, rec, direction) * (1.0 - weight) + self.second.pdf(r, rec, direction) * weight
+    }
+
+    pub fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        let weight: f64 = self.weight(rec);
+        self.first.emitted(r, rec) * (1.0 - weight) + self.second.emitted(r, rec) * weight
+    }
+
+    pub fn opacity(&self, rec: &HitRecord) -> f64 {
+        let weight: f64 = self.weight(rec);
+        self.first.opacity(rec) * (1.0 - weight) + self.second.opacity(rec) * weight
+    }
+
+    // Only the transmitting side ever enters the inside, so an opaque side defers to the
+    // other. Two sides with different media have no single inside and are left alone.
+    pub fn medium(&self) -> Option<Medium> {
+        match (self.first.medium(), self.second.medium()) {
+            (Some(first), Some(second)) if first != second => None,
+            (first, second) => first.or(second),
+        }
+    }
+
+    pub fn with_outside_index(&self, outside_index: f64) -> Self {
+        Self { first: Arc::new(self.first.with_outside_index(outside_index)),
+               second: Arc::new(self.second.with_outside_index(outside_index)),
+               weight: self.weight.clone() }
+    }
+}
+
+impl Clearcoat {
+    pub fn new(base: &Material, refraction_index: f64, roughness: f64) -> Self {
+        let (alpha, _): (f64, f64) = microfacet::roughness_to_alpha(roughness, 0.0);
+        Self { base: Arc::new(base.clone()), refraction_index, alpha }
+    }
+
+    fn transmittance(&self, cosine: f64) -> f64 {
+        1.0 - microfacet::fresnel_dielectric(cosine, self.refraction_index)
+    }
+
+    // Probability of sampling the coat rather than the base
+    fn coat_probability(&self, wo: &Vec3) -> f64 {
+        (1.0 - self.transmittance(wo.z)).clamp(0.1, 0.9)
+    }
+
+    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<ScatterRecord> {
+        let (uvw, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let coat_probability: f64 = self.coat_probability(&wo);
+        let smooth: bool = microfacet::is_smooth(self.alpha, self.alpha);
+
+        let direction: Vec3 = if rng.gen::<f64>() < coat_probability {
+            if smooth {
+                let reflected: Vec3 = material::reflect(&r.direction.normalize(), &rec.normal);
+                let fresnel: f64 = 1.0 - self.transmittance(wo.z);
+                return Some(ScatterRecord { scattered: Ray::new(&rec.p, &reflected, r.time),
+                                            attenuation: Color::new(fresnel, fresnel, fresnel) / coat_probability,
+                                            pdf: None });
+            }
+            let h: Vec3 = microfacet::sample_visible_normal(&wo, self.alpha, self.alpha, rng.gen(), rng.gen());
+            uvw.local(&material::reflect(&(-wo), &h))
+        } else {
+            let srec: ScatterRecord = self.base.scatter(r, rec, rng)?;
+            if srec.pdf.is_none() {
+                let wi: Vec3 = uvw.to_local(&srec.scattered.direction.normalize());
+                let transmittance: f64 = self.transmittance(wo.z) * self.transmittance(wi.z.abs());
+                return Some(ScatterRecord { attenuation: srec.attenuation * (transmittance / (1.0 - coat_probability)), ..srec });
+            }
+            srec.scattered.direction
+        };
+
+        let pdf: f64 = self.pdf(r, rec, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord { scattered: Ray::new(&rec.p, &direction, r.time),
+                             attenuation: self.eval(r, rec, &direction) / pdf,
+                             pdf: Some(pdf) })
+    }
+
+    pub fn eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let (uvw, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        let wi: Vec3 = uvw.to_local(&direction.normalize());
+        let base: Color = self.base.eval(r, rec, direction) * (self.transmittance(wo.z) * self.transmittance(wi.z.abs()));
+        if wo.z <= 0.0 || wi.z <= 0.0 || microfacet::is_smooth(self.alpha, self.alpha) {
+            return base;
+        }
+        let h: Vec3 = (wo + wi).normalize();
+        let coat: f64 = microfacet::fresnel_dielectric(wo.dot(&h), self.refraction_index)
+            * microfacet::distribution(&h, self.alpha, self.alpha) * microfacet::smith_g2(&wo, &wi, self.alpha, self.alpha) / (4.0 * wo.z);
+        base + Color::new(coat, coat, coat)
+    }
+
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let (uvw, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        let wi: Vec3 = uvw.to_local(&direction.normalize());
+        let coat_probability: f64 = self.coat_probability(&wo);
+        let base: f64 = (1.0 - coat_probability) * self.base.pdf(r, rec, direction);
+        if wo.z <= 0.0 || wi.z <= 0.0 || microfacet::is_smooth(self.alpha, self.alpha) {
+            return base;
+        }
+        let h: Vec3 = (wo + wi).normalize();
+        base + coat_probability * microfacet::visible_normal_pdf(&wo, &h, self.alpha, self.alpha) / (4.0 * wo.dot(&h))
+    }
+}
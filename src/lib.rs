@@ -8,8 +8,37 @@ pub mod material;
 pub mod raytracer;
 pub mod moving_sphere;
 pub mod texture;
+pub mod distribution;
+pub mod background;
+pub mod sky;
+pub mod delta_light;
+pub mod aabb;
+pub mod light_tree;
+pub mod ies;
+pub mod image_texture;
+pub mod perlin;
+pub mod cellular;
 pub mod scene;
 pub mod rectangle;
 pub mod hbox;
+pub mod onb;
+pub mod microfacet;
+pub mod medium;
+pub mod principled;
+pub mod layered;
+pub mod thin_film;
+pub mod sheen;
+pub mod spectrum;
+pub mod bump;
 
-pub type PRNG = rand_xoshiro::Xoroshiro128Plus;
\ No newline at end of file
+pub type PRNG = rand_xoshiro::Xoroshiro128Plus;
+
+// Uniform value in [0, 1) from the bits of `x` (splitmix64 finalizer), for hashing
+// cells and hit points into repeatable random numbers
+pub(crate) fn hash_to_unit(x: u64) -> f64 {
+    let mut x: u64 = x;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^= x >> 31;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,199 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::ray::Ray;
+use crate::vec3::{Vec3, Point3};
+use crate::PRNG;
+
+// Where a light is and how much it emits, to decide how often to sample it
+#[derive(Debug, Copy, Clone)]
+pub struct LightBounds {
+    pub bounds: Aabb,
+    pub power: f64,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum NodeKind {
+    Leaf(usize),
+    Interior(usize, usize),
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    bounds: Aabb,
+    power: f64,
+    kind: NodeKind,
+    parent: Option<usize>,
+}
+
+impl Node {
+    // Power over squared distance, which stops growing once `p` is inside the node's extent.
+    // The orientation of the lights is not taken into account.
+    fn importance(&self, p: &Point3) -> f64 {
+        if self.power <= 0.0 {
+            return 0.0;
+        }
+        if !self.bounds.is_finite() {
+            return self.power;
+        }
+        let distance_squared: f64 = (self.bounds.centroid() - *p).sqlen();
+        let radius_squared: f64 = ((self.bounds.maximum - self.bounds.minimum) * 0.5).sqlen();
+        self.power / distance_squared.max(radius_squared).max(1e-12)
+    }
+}
+
+// Bounding volume hierarchy over the lights: each sample walks down the tree, choosing
+// a child by its importance from the shaded point, so bright and close lights are picked
+// more often and the cost grows with the log of the number of lights.
+// Lights without `light_bounds` are treated as everywhere, with the mean power of the others.
+pub struct LightTree<'a> {
+    lights: Vec<&'a dyn Hittable>,
+    nodes: Vec<Node>,
+    // Leaf node of each light
+    leaves: Vec<usize>,
+}
+
+impl<'a> LightTree<'a> {
+    pub fn new(lights: &'a HittableList) -> Self {
+        let lights: Vec<&'a dyn Hittable> = lights.objects.iter().map(|light| light.as_ref()).collect();
+        let known: Vec<Option<LightBounds>> = lights.iter().map(|light| light.light_bounds()).collect();
+        let powers: Vec<f64> = known.iter().flatten().map(|light| light.power).collect();
+        let mean_power: f64 = if powers.is_empty() { 1.0 } else { powers.iter().sum::<f64>() / powers.len() as f64 };
+        let bounds: Vec<LightBounds> = known.into_iter().map(|light| {
+            light.unwrap_or(LightBounds { bounds: Aabb::infinite(), power: mean_power })
+        }).collect();
+        let mut tree: LightTree = Self { lights, nodes: Vec::new(), leaves: vec![0; bounds.len()] };
+        if !bounds.is_empty() {
+            let mut indices: Vec<usize> = (0..bounds.len()).collect();
+            tree.build(&mut indices, &bounds, None);
+        }
+        tree
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    // Splits at the median centroid along the longest axis, returns the index of the subtree's root
+    fn build(&mut self, indices: &mut [usize], bounds: &[LightBounds], parent: Option<usize>) -> usize {
+        let index: usize = self.nodes.len();
+        if indices.len() == 1 {
+            let light: usize = indices[0];
+            self.nodes.push(Node { bounds: bounds[light].bounds, power: bounds[light].power, kind: NodeKind::Leaf(light), parent });
+            self.leaves[light] = index;
+            return index;
+        }
+        let centroids: Aabb = indices.iter().skip(1).fold(Aabb::new(&bounds[indices[0]].bounds.centroid(), &bounds[indices[0]].bounds.centroid()), |acc, i| {
+            let centroid: Point3 = bounds[*i].bounds.centroid();
+            acc.surrounding_box(&Aabb::new(&centroid, &centroid))
+        });
+        let extent: Vec3 = centroids.maximum - centroids.minimum;
+        let axis = |p: &Point3| -> f64 {
+            if extent.x >= extent.y && extent.x >= extent.z { p.x } else if extent.y >= extent.z { p.y } else { p.z }
+        };
+        indices.sort_by(|a, b| axis(&bounds[*a].bounds.centroid()).total_cmp(&axis(&bounds[*b].bounds.centroid())));
+
+        // Placeholder until both children are built
+        self.nodes.push(Node { bounds: Aabb::infinite(), power: 0.0, kind: NodeKind::Leaf(0), parent });
+        let middle: usize = indices.len() / 2;
+        let (left_indices, right_indices): (&mut [usize], &mut [usize]) = indices.split_at_mut(middle);
+        let left: usize = self.build(left_indices, bounds, Some(index));
+        let right: usize = self.build(right_indices, bounds, Some(index));
+        self.nodes[index] = Node { bounds: self.nodes[left].bounds.surrounding_box(&self.nodes[right].bounds),
+                                   power: self.nodes[left].power + self.nodes[right].power,
+                                   kind: NodeKind::Interior(left, right),
+                                   parent };
+        index
+    }
+
+    // Chances of going down to the left and right child from `p`
+    fn child_probabilities(&self, left: usize, right: usize, p: &Point3) -> (f64, f64) {
+        let left_importance: f64 = self.nodes[left].importance(p);
+        let right_importance: f64 = self.nodes[right].importance(p);
+        let total: f64 = left_importance + right_importance;
+        if total <= 0.0 {
+            return (0.5, 0.5);
+        }
+        (left_importance / total, right_importance / total)
+    }
+
+    // Picks a light for `p`, with the probability of having picked it
+    pub fn sample(&self, p: &Point3, rng: &mut PRNG) -> Option<(usize, f64)> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut u: f64 = rng.gen::<f64>();
+        let mut node: usize = 0;
+        let mut pmf: f64 = 1.0;
+        loop {
+            match self.nodes[node].kind {
+                NodeKind::Leaf(light) => return Some((light, pmf)),
+                NodeKind::Interior(left, right) => {
+                    let (p_left, p_right): (f64, f64) = self.child_probabilities(left, right, p);
+                    if u < p_left {
+                        u /= p_left;
+                        pmf *= p_left;
+                        node = left;
+                    } else {
+                        u = ((u - p_left) / p_right).min(1.0 - 1e-12);
+                        pmf *= p_right;
+                        node = right;
+                    }
+                }
+            }
+        }
+    }
+
+    // Probability of `sample` picking `light` from `p`
+    pub fn pmf(&self, p: &Point3, light: usize) -> f64 {
+        let mut pmf: f64 = 1.0;
+        let mut node: usize = self.leaves[light];
+        while let Some(parent) = self.nodes[node].parent {
+            if let NodeKind::Interior(left, right) = self.nodes[parent].kind {
+                let (p_left, p_right): (f64, f64) = self.child_probabilities(left, right, p);
+                pmf *= if node == left { p_left } else { p_right };
+            }
+            node = parent;
+        }
+        pmf
+    }
+
+    // Solid angle pdf of `random`, visiting only the lights whose bounds the direction crosses
+    pub fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let r: Ray = Ray::new(origin, direction, 0.0);
+        let mut pdf: f64 = 0.0;
+        let mut stack: Vec<(usize, f64)> = vec![(0, 1.0)];
+        while let Some((node, pmf)) = stack.pop() {
+            if !self.nodes[node].bounds.hit(&r, 0.001, f64::INFINITY) {
+                continue;
+            }
+            match self.nodes[node].kind {
+                NodeKind::Leaf(light) => {
+                    pdf += pmf * self.lights[light].pdf_value(origin, direction);
+                }
+                NodeKind::Interior(left, right) => {
+                    let (p_left, p_right): (f64, f64) = self.child_probabilities(left, right, origin);
+                    if p_left > 0.0 {
+                        stack.push((left, pmf * p_left));
+                    }
+                    if p_right > 0.0 {
+                        stack.push((right, pmf * p_right));
+                    }
+                }
+            }
+        }
+        pdf
+    }
+
+    pub fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        match self.sample(origin, rng) {
+            Some((light, _)) => self.lights[light].random(origin, rng),
+            None => Vec3::new(1.0, 0.0, 0.0),
+        }
+    }
+}
@@ -2,8 +2,11 @@ use std::time::Instant;
 use std::env;
 
 use ray_tracing_weekend::vec3::Color;
+use ray_tracing_weekend::background::Background;
+use ray_tracing_weekend::delta_light::DeltaLight;
 use ray_tracing_weekend::hittable::Hittable;
-use ray_tracing_weekend::raytracer::render;
+use ray_tracing_weekend::hittable_list::HittableList;
+use ray_tracing_weekend::raytracer::{render, Scene};
 use ray_tracing_weekend::scene::*;
 
 fn main() {
@@ -27,14 +30,30 @@ fn main() {
     // World
     //let world: Box<dyn Hittable> = Box::new(random_scene(true));
     let world: Box<dyn Hittable> = Box::new(cornell());
-    let background: Color = Color::new(0.0, 0.0, 0.0);
+    let lights: HittableList = cornell_lights();
+    let delta_lights: Vec<DeltaLight> = Vec::new();
+    //let world: Box<dyn Hittable> = Box::new(many_lights());
+    //let lights: HittableList = many_lights_lights();
+    //let world: Box<dyn Hittable> = Box::new(spotlights());
+    //let delta_lights: Vec<DeltaLight> = spotlights_delta_lights();
+    //let world: Box<dyn Hittable> = Box::new(photometric("luminaire.ies"));
+    //let lights: HittableList = photometric_lights("luminaire.ies");
+    //let delta_lights: Vec<DeltaLight> = photometric_delta_lights("luminaire.ies");
+    let background: Background = Background::new_constant(&Color::new(0.0, 0.0, 0.0));
+    //let world: Box<dyn Hittable> = Box::new(environment());
+    //let lights: HittableList = HittableList::new(Vec::new());
+    //let background: Background = environment_background("environment.hdr", 0.0, 1.0);
+    //let background: Background = Background::new_sky(30.0, 30.0, 3.0, 0.3);
+    //let background: Background = Background::new_gradient(&Color::new(1.0, 1.0, 1.0), &Color::new(0.5, 0.7, 1.0));
 
     // Camera
     //let cam = random_scene_cam(aspect_ratio);
     //let cam = two_spheres_cam(aspect_ratio);
     let cam = cornell_cam(aspect_ratio);
 
-    render(filename, cam, &world, background, image_width, image_height, samples_per_pixel, max_depth);
+    let scene: Scene = Scene::new(world, lights, delta_lights, background);
+
+    render(filename, cam, &scene, image_width, image_height, samples_per_pixel, max_depth);
 
     eprintln!("Fatto! Hai perso {} secondi della tua vita", start.elapsed().as_secs());
 }
@@ -1,69 +1,270 @@
+use std::sync::Arc;
+
 use rand::prelude::Rng;
 
 use crate::vec3::{Vec3, Point3, Color};
 use crate::ray::Ray;
 use crate::hittable::HitRecord;
 use crate::PRNG;
-use crate::texture::Texture;
+use crate::texture::{Texture, Footprint};
+use crate::onb::Onb;
+use crate::microfacet;
+use crate::medium::Medium;
+use crate::principled::Principled;
+use crate::layered::{Mix, Clearcoat};
+use crate::thin_film::ThinFilm;
+use crate::bump::{Bumped, Perturbation};
+use crate::sheen::Sheen;
+use crate::spectrum;
+use crate::background::luminance;
+use crate::ies::IesProfile;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum Material {
     Lambertian { albedo: Color },
     Textured { texture: Texture },
+    OrenNayar { albedo: Texture, a: f64, b: f64 },
     Metal { albedo: Color, fuzz: f64 },
-    Dielectric { refraction_index: f64 },
-    Light { albedo: Color },
+    Conductor { eta: Color, k: Color, alpha_x: f64, alpha_y: f64 },
+    Dielectric { refraction_index: f64, absorption: Color, priority: u32 },
+    RoughDielectric { refraction_index: f64, alpha_x: f64, alpha_y: f64, absorption: Color, priority: u32 },
+    Subsurface { refraction_index: f64, alpha: f64, absorption: Color, scattering: Color },
+    Principled(Box<Principled>),
+    Mix(Mix),
+    Clearcoat(Clearcoat),
+    ThinFilm(ThinFilm),
+    Bumped(Bumped),
+    Sheen(Sheen),
+    Cutout { base: Arc<Material>, opacity: Texture },
+    // `profile` scales the radiance by direction, with the nadir along the normal
+    Light { emit: Texture, intensity: f64, two_sided: bool, profile: Option<Arc<IesProfile>> },
 }
 
+// Result of sampling a material. `attenuation` is the path throughput weight
+// (bsdf * cos / pdf), `pdf` is the solid angle pdf of `scattered`, or None for
+// specular lobes that cannot be evaluated for an arbitrary direction.
+#[derive(Debug, Copy, Clone)]
+pub struct ScatterRecord {
+    pub scattered: Ray,
+    pub attenuation: Color,
+    pub pdf: Option<f64>,
+}
 
-fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
+
+pub(crate) fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
     *v - *n * (2.0 * v.dot(n))
 }
 
-fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
+pub(crate) fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
     let cos_theta = ((-*uv).dot(n)).min(1.0);
     let r_out_perp = (*uv + *n * cos_theta) * etai_over_etat;
     let r_out_parallel = *n * (-1.0 * (1.0 - r_out_perp.sqlen()).abs().sqrt());
     r_out_perp + r_out_parallel
 }
 
-fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
-    let mut r0: f64 = (1.0 - refraction_index) / (1.0 + refraction_index);
-    r0 = r0 * r0;
-    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+// Cosine-weighted hemisphere, which is also the Lambertian bsdf * cos up to the albedo
+fn diffuse_pdf(rec: &HitRecord, direction: &Vec3) -> f64 {
+    let cosine: f64 = rec.normal.dot(&direction.normalize());
+    if cosine <= 0.0 { 0.0 } else { cosine / std::f64::consts::PI }
+}
+
+fn scatter_diffuse(r: &Ray, rec: &HitRecord, albedo: &Color, rng: &mut PRNG) -> ScatterRecord {
+    let uvw: Onb = Onb::build_from_w(&rec.normal);
+    let direction: Vec3 = uvw.local(&Vec3::random_cosine_direction(rng));
+    ScatterRecord { scattered: Ray::new(&rec.p, &direction, r.time),
+                    attenuation: *albedo,
+                    pdf: Some(diffuse_pdf(rec, &direction)) }
+}
+
+// Oren-Nayar rough diffuse relative to Lambertian, in the qualitative form used by pbrt
+fn oren_nayar_factor(r: &Ray, rec: &HitRecord, direction: &Vec3, a: f64, b: f64) -> f64 {
+    let (uvw, wo): (Onb, Vec3) = local_frame(r, rec);
+    let wi: Vec3 = uvw.to_local(&direction.normalize());
+    let sin_i: f64 = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+    let sin_o: f64 = (1.0 - wo.z * wo.z).max(0.0).sqrt();
+    let max_cos: f64 = if sin_i > 1e-4 && sin_o > 1e-4 {
+        ((wi.x * wo.x + wi.y * wo.y) / (sin_i * sin_o)).max(0.0)
+    } else {
+        0.0
+    };
+    let (sin_alpha, tan_beta): (f64, f64) = if wi.z.abs() > wo.z.abs() {
+        (sin_o, sin_i / wi.z.abs())
+    } else {
+        (sin_i, sin_o / wo.z.abs())
+    };
+    a + b * max_cos * sin_alpha * tan_beta
+}
+
+// Outgoing direction (towards the viewer) in the local shading frame, x along dp/du
+pub(crate) fn local_frame(r: &Ray, rec: &HitRecord) -> (Onb, Vec3) {
+    let uvw: Onb = Onb::build_from_w_and_tangent(&rec.normal, &rec.dpdu);
+    let wo: Vec3 = uvw.to_local(&(-r.direction.normalize()));
+    (uvw, wo)
+}
+
+// GGX reflection, `fresnel` gives the reflectance for the cosine between the view and the microfacet normal
+fn scatter_conductor(r: &Ray, rec: &HitRecord, fresnel: &dyn Fn(f64) -> Color, alpha_x: f64, alpha_y: f64, rng: &mut PRNG) -> Option<ScatterRecord> {
+    let (uvw, wo): (Onb, Vec3) = local_frame(r, rec);
+    if wo.z <= 0.0 {
+        return None;
+    }
+    if microfacet::is_smooth(alpha_x, alpha_y) {
+        let reflected: Vec3 = reflect(&r.direction.normalize(), &rec.normal);
+        return Some(ScatterRecord { scattered: Ray::new(&rec.p, &reflected, r.time),
+                                    attenuation: fresnel(wo.z),
+                                    pdf: None });
+    }
+    let h: Vec3 = microfacet::sample_visible_normal(&wo, alpha_x, alpha_y, rng.gen(), rng.gen());
+    let wi: Vec3 = reflect(&(-wo), &h);
+    if wi.z <= 0.0 {
+        return None;
+    }
+    let reflectance: Color = fresnel(wo.dot(&h));
+    let masking: f64 = microfacet::smith_g2(&wo, &wi, alpha_x, alpha_y) / microfacet::smith_g1(&wo, alpha_x, alpha_y);
+    let direction: Vec3 = uvw.local(&wi);
+    Some(ScatterRecord { scattered: Ray::new(&rec.p, &direction, r.time),
+                         attenuation: reflectance * masking,
+                         pdf: Some(microfacet::visible_normal_pdf(&wo, &h, alpha_x, alpha_y) / (4.0 * wo.dot(&h))) })
+}
+
+fn eval_conductor(r: &Ray, rec: &HitRecord, direction: &Vec3, fresnel: &dyn Fn(f64) -> Color, alpha_x: f64, alpha_y: f64) -> Color {
+    let (uvw, wo): (Onb, Vec3) = local_frame(r, rec);
+    let wi: Vec3 = uvw.to_local(&direction.normalize());
+    if wo.z <= 0.0 || wi.z <= 0.0 || microfacet::is_smooth(alpha_x, alpha_y) {
+        return Color::default();
+    }
+    let h: Vec3 = (wo + wi).normalize();
+    fresnel(wo.dot(&h))
+        * (microfacet::distribution(&h, alpha_x, alpha_y) * microfacet::smith_g2(&wo, &wi, alpha_x, alpha_y) / (4.0 * wo.z))
+}
+
+fn pdf_conductor(r: &Ray, rec: &HitRecord, direction: &Vec3, alpha_x: f64, alpha_y: f64) -> f64 {
+    let (uvw, wo): (Onb, Vec3) = local_frame(r, rec);
+    let wi: Vec3 = uvw.to_local(&direction.normalize());
+    if wo.z <= 0.0 || wi.z <= 0.0 || microfacet::is_smooth(alpha_x, alpha_y) {
+        return 0.0;
+    }
+    let h: Vec3 = (wo + wi).normalize();
+    microfacet::visible_normal_pdf(&wo, &h, alpha_x, alpha_y) / (4.0 * wo.dot(&h))
+}
+
+// Walter et al. 2007 rough dielectric. The shading normal always faces `wo`,
+// so `eta` is the relative index eta_t / eta_i across the surface from that side.
+fn relative_eta(rec: &HitRecord, refraction_index: f64) -> f64 {
+    if rec.front_face { refraction_index } else { 1.0 / refraction_index }
+}
+
+// Generalized half vector of a transmission, oriented to the `wo` side
+fn transmission_half_vector(wo: &Vec3, wi: &Vec3, eta: f64) -> Vec3 {
+    let h: Vec3 = (*wo + *wi * eta).normalize();
+    if h.z < 0.0 { -h } else { h }
+}
+
+pub(crate) fn scatter_rough_dielectric(r: &Ray, rec: &HitRecord, refraction_index: f64, alpha_x: f64, alpha_y: f64, rng: &mut PRNG) -> Option<ScatterRecord> {
+    let (uvw, wo): (Onb, Vec3) = local_frame(r, rec);
+    if wo.z <= 0.0 {
+        return None;
+    }
+    let eta: f64 = relative_eta(rec, refraction_index);
+    let h: Vec3 = microfacet::sample_visible_normal(&wo, alpha_x, alpha_y, rng.gen(), rng.gen());
+    let cos_oh: f64 = wo.dot(&h);
+    let fresnel: f64 = microfacet::fresnel_dielectric(cos_oh, eta);
+    let visible_pdf: f64 = microfacet::visible_normal_pdf(&wo, &h, alpha_x, alpha_y);
+
+    let (wi, pdf): (Vec3, f64) = if rng.gen::<f64>() < fresnel {
+        let wi: Vec3 = reflect(&(-wo), &h);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        (wi, fresnel * visible_pdf / (4.0 * cos_oh))
+    } else {
+        let wi: Vec3 = refract(&(-wo), &h, 1.0 / eta);
+        if wi.z >= 0.0 {
+            return None;
+        }
+        let denom: f64 = cos_oh + eta * wi.dot(&h);
+        (wi, (1.0 - fresnel) * visible_pdf * eta * eta * wi.dot(&h).abs() / (denom * denom))
+    };
+    // The Fresnel term and the visible normal density cancel with the pdf
+    let masking: f64 = microfacet::smith_g2(&wo, &wi, alpha_x, alpha_y) / microfacet::smith_g1(&wo, alpha_x, alpha_y);
+    let direction: Vec3 = uvw.local(&wi);
+    Some(ScatterRecord { scattered: Ray::new(&rec.p, &direction, r.time),
+                         attenuation: Color::new(masking, masking, masking),
+                         pdf: Some(pdf) })
+}
+
+pub(crate) fn eval_rough_dielectric(r: &Ray, rec: &HitRecord, direction: &Vec3, refraction_index: f64, alpha_x: f64, alpha_y: f64) -> Color {
+    let (uvw, wo): (Onb, Vec3) = local_frame(r, rec);
+    let wi: Vec3 = uvw.to_local(&direction.normalize());
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return Color::default();
+    }
+    let eta: f64 = relative_eta(rec, refraction_index);
+    let value: f64 = if wi.z > 0.0 {
+        let h: Vec3 = (wo + wi).normalize();
+        microfacet::fresnel_dielectric(wo.dot(&h), eta)
+            * microfacet::distribution(&h, alpha_x, alpha_y) * microfacet::smith_g2(&wo, &wi, alpha_x, alpha_y) / (4.0 * wo.z)
+    } else {
+        let h: Vec3 = transmission_half_vector(&wo, &wi, eta);
+        let cos_oh: f64 = wo.dot(&h);
+        let cos_ih: f64 = wi.dot(&h);
+        if cos_oh <= 0.0 || cos_ih >= 0.0 {
+            return Color::default();
+        }
+        let denom: f64 = cos_oh + eta * cos_ih;
+        (1.0 - microfacet::fresnel_dielectric(cos_oh, eta))
+            * microfacet::distribution(&h, alpha_x, alpha_y) * microfacet::smith_g2(&wo, &wi, alpha_x, alpha_y)
+            * eta * eta * cos_ih.abs() * cos_oh / (wo.z * denom * denom)
+    };
+    Color::new(value, value, value)
+}
+
+pub(crate) fn pdf_rough_dielectric(r: &Ray, rec: &HitRecord, direction: &Vec3, refraction_index: f64, alpha_x: f64, alpha_y: f64) -> f64 {
+    let (uvw, wo): (Onb, Vec3) = local_frame(r, rec);
+    let wi: Vec3 = uvw.to_local(&direction.normalize());
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return 0.0;
+    }
+    let eta: f64 = relative_eta(rec, refraction_index);
+    if wi.z > 0.0 {
+        let h: Vec3 = (wo + wi).normalize();
+        let cos_oh: f64 = wo.dot(&h);
+        microfacet::fresnel_dielectric(cos_oh, eta) * microfacet::visible_normal_pdf(&wo, &h, alpha_x, alpha_y) / (4.0 * cos_oh)
+    } else {
+        let h: Vec3 = transmission_half_vector(&wo, &wi, eta);
+        let cos_oh: f64 = wo.dot(&h);
+        let cos_ih: f64 = wi.dot(&h);
+        if cos_oh <= 0.0 || cos_ih >= 0.0 {
+            return 0.0;
+        }
+        let denom: f64 = cos_oh + eta * cos_ih;
+        (1.0 - microfacet::fresnel_dielectric(cos_oh, eta)) * microfacet::visible_normal_pdf(&wo, &h, alpha_x, alpha_y)
+            * eta * eta * cos_ih.abs() / (denom * denom)
+    }
 }
 
 impl Material {
-    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<(Ray, Color)> {
+    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<ScatterRecord> {
         match self {
             Self::Lambertian { albedo } => {
-                let mut scatter_direction: Vec3 = rec.normal + Vec3::random_in_unit_sphere(rng);
-                if scatter_direction.near_zero() {
-                    scatter_direction = rec.normal;
-                }
-                let scattered: Ray = Ray::new(&rec.p, &scatter_direction, r.time);
-                let attenuation: Color = *albedo;
-                Some((scattered, attenuation))
+                Some(scatter_diffuse(r, rec, albedo, rng))
             }
             Self::Textured { texture } => {
-                let mut scatter_direction: Vec3 = rec.normal + Vec3::random_in_unit_sphere(rng);
-                if scatter_direction.near_zero() {
-                    scatter_direction = rec.normal;
-                }
-                let scattered: Ray = Ray::new(&rec.p, &scatter_direction, r.time);
-                let attenuation: Color = texture.value(rec.u, rec.v, &rec.p);
-                Some((scattered, attenuation))
+                let attenuation: Color = texture.sample(rec);
+                Some(scatter_diffuse(r, rec, &attenuation, rng))
+            }
+            Self::OrenNayar { albedo, a, b } => {
+                let mut srec: ScatterRecord = scatter_diffuse(r, rec, &albedo.sample(rec), rng);
+                srec.attenuation = srec.attenuation * oren_nayar_factor(r, rec, &srec.scattered.direction, *a, *b);
+                Some(srec)
             }
             Self::Metal { albedo, fuzz } => {
-                let reflected: Vec3 = reflect(&r.direction, &rec.normal);
-                let scattered: Ray = Ray::new(&rec.p, &(reflected + Vec3::random_in_unit_sphere(rng) * *fuzz), r.time);
-                let attenuation: Color = *albedo;
-                if scattered.direction.dot(&rec.normal) > 0.0 {
-                    return Some((scattered, attenuation));
-                }
-                None
+                scatter_conductor(r, rec, &|_| *albedo, *fuzz, *fuzz, rng)
             }
-            Self::Dielectric { refraction_index } => {
+            Self::Conductor { eta, k, alpha_x, alpha_y } => {
+                scatter_conductor(r, rec, &|cos| microfacet::fresnel_conductor(cos, eta, k), *alpha_x, *alpha_y, rng)
+            }
+            Self::Dielectric { refraction_index, .. } => {
                 let attenuation: Color = Color::new(1.0, 1.0, 1.0);
                 let refraction_ratio: f64 = if rec.front_face { 1.0 / refraction_index } else { *refraction_index };
                 let unit_direction = r.direction.normalize();
@@ -71,26 +272,100 @@ impl Material {
                 let sin_theta: f64 = (1.0 - (cos_theta * cos_theta)).sqrt();
                 let cannot_refract: bool = refraction_ratio * sin_theta > 1.0;
 
-                if cannot_refract || reflectance(cos_theta, refraction_ratio) > rng.gen::<f64>() {
+                if cannot_refract || microfacet::fresnel_dielectric(cos_theta, 1.0 / refraction_ratio) > rng.gen::<f64>() {
                     let reflected: Vec3 = reflect(&unit_direction, &rec.normal);
                     let scattered: Ray = Ray::new(&rec.p, &reflected, r.time);
-                    Some((scattered, attenuation))
+                    Some(ScatterRecord { scattered, attenuation, pdf: None })
                 } else {
                     let direction: Vec3 = refract(&unit_direction, &rec.normal, refraction_ratio);
                     let scattered: Ray = Ray::new(&rec.p, &direction, r.time);
-                    Some((scattered, attenuation))
+                    Some(ScatterRecord { scattered, attenuation, pdf: None })
+                }
+            }
+            Self::RoughDielectric { refraction_index, alpha_x, alpha_y, .. } => {
+                if microfacet::is_smooth(*alpha_x, *alpha_y) {
+                    return Self::new_dielectric(*refraction_index).scatter(r, rec, rng);
                 }
+                scatter_rough_dielectric(r, rec, *refraction_index, *alpha_x, *alpha_y, rng)
+            }
+            Self::Subsurface { refraction_index, alpha, .. } => {
+                // The random walk inside is done by the integrator, this is just the boundary
+                if microfacet::is_smooth(*alpha, *alpha) {
+                    return Self::new_dielectric(*refraction_index).scatter(r, rec, rng);
+                }
+                scatter_rough_dielectric(r, rec, *refraction_index, *alpha, *alpha, rng)
+            }
+            Self::Principled(principled) => {
+                principled.scatter(r, rec, rng)
             }
-            Self::Light { albedo: _albedo } => {
+            Self::Mix(mix) => {
+                mix.scatter(r, rec, rng)
+            }
+            Self::Clearcoat(coat) => {
+                coat.scatter(r, rec, rng)
+            }
+            Self::ThinFilm(film) => {
+                film.scatter(r, rec, rng)
+            }
+            Self::Sheen(sheen) => {
+                sheen.scatter(r, rec, rng)
+            }
+            Self::Bumped(bumped) => {
+                bumped.scatter(r, rec, rng)
+            }
+            Self::Cutout { base, .. } => {
+                base.scatter(r, rec, rng)
+            }
+            Self::Light { .. } => {
                 None
             }
         }
     }
 
-    pub fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+    // Bsdf times cosine for light leaving along `direction`. Zero for specular materials.
+    pub fn eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
         match self {
-            Self::Light { albedo } => {
-                *albedo
+            Self::Lambertian { albedo } => {
+                *albedo * diffuse_pdf(rec, direction)
+            }
+            Self::Textured { texture } => {
+                texture.sample(rec) * diffuse_pdf(rec, direction)
+            }
+            Self::OrenNayar { albedo, a, b } => {
+                albedo.sample(rec) * (oren_nayar_factor(r, rec, direction, *a, *b) * diffuse_pdf(rec, direction))
+            }
+            Self::Metal { albedo, fuzz } => {
+                eval_conductor(r, rec, direction, &|_| *albedo, *fuzz, *fuzz)
+            }
+            Self::Conductor { eta, k, alpha_x, alpha_y } => {
+                eval_conductor(r, rec, direction, &|cos| microfacet::fresnel_conductor(cos, eta, k), *alpha_x, *alpha_y)
+            }
+            Self::RoughDielectric { refraction_index, alpha_x, alpha_y, .. } if !microfacet::is_smooth(*alpha_x, *alpha_y) => {
+                eval_rough_dielectric(r, rec, direction, *refraction_index, *alpha_x, *alpha_y)
+            }
+            Self::Subsurface { refraction_index, alpha, .. } if !microfacet::is_smooth(*alpha, *alpha) => {
+                eval_rough_dielectric(r, rec, direction, *refraction_index, *alpha, *alpha)
+            }
+            Self::Principled(principled) => {
+                principled.eval(r, rec, direction)
+            }
+            Self::Mix(mix) => {
+                mix.eval(r, rec, direction)
+            }
+            Self::Clearcoat(coat) => {
+                coat.eval(r, rec, direction)
+            }
+            Self::ThinFilm(film) => {
+                film.eval(r, rec, direction)
+            }
+            Self::Sheen(sheen) => {
+                sheen.eval(r, rec, direction)
+            }
+            Self::Bumped(bumped) => {
+                bumped.eval(r, rec, direction)
+            }
+            Self::Cutout { base, .. } => {
+                base.eval(r, rec, direction)
             }
             _ => {
                 Color::default()
@@ -98,24 +373,371 @@ impl Material {
         }
     }
 
+    // Solid angle pdf of `scatter` producing `direction`
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        match self {
+            Self::Lambertian { .. } | Self::Textured { .. } | Self::OrenNayar { .. } => {
+                diffuse_pdf(rec, direction)
+            }
+            Self::Metal { fuzz, .. } => {
+                pdf_conductor(r, rec, direction, *fuzz, *fuzz)
+            }
+            Self::Conductor { alpha_x, alpha_y, .. } => {
+                pdf_conductor(r, rec, direction, *alpha_x, *alpha_y)
+            }
+            Self::RoughDielectric { refraction_index, alpha_x, alpha_y, .. } if !microfacet::is_smooth(*alpha_x, *alpha_y) => {
+                pdf_rough_dielectric(r, rec, direction, *refraction_index, *alpha_x, *alpha_y)
+            }
+            Self::Subsurface { refraction_index, alpha, .. } if !microfacet::is_smooth(*alpha, *alpha) => {
+                pdf_rough_dielectric(r, rec, direction, *refraction_index, *alpha, *alpha)
+            }
+            Self::Principled(principled) => {
+                principled.pdf(r, rec, direction)
+            }
+            Self::Mix(mix) => {
+                mix.pdf(r, rec, direction)
+            }
+            Self::Clearcoat(coat) => {
+                coat.pdf(r, rec, direction)
+            }
+            Self::ThinFilm(film) => {
+                film.pdf(r, rec, direction)
+            }
+            Self::Sheen(sheen) => {
+                sheen.pdf(r, rec, direction)
+            }
+            Self::Bumped(bumped) => {
+                bumped.pdf(r, rec, direction)
+            }
+            Self::Cutout { base, .. } => {
+                base.pdf(r, rec, direction)
+            }
+            _ => {
+                0.0
+            }
+        }
+    }
+
+    // Interior medium of dielectrics, tracked by the integrator for absorption and nesting
+    pub fn medium(&self) -> Option<Medium> {
+        match self {
+            Self::Dielectric { refraction_index, absorption, priority }
+            | Self::RoughDielectric { refraction_index, absorption, priority, .. } => {
+                Some(Medium::new(*refraction_index, absorption, &Color::default(), *priority))
+            }
+            Self::Subsurface { refraction_index, absorption, scattering, .. } => {
+                Some(Medium::new(*refraction_index, absorption, scattering, 0))
+            }
+            Self::Mix(mix) => {
+                mix.medium()
+            }
+            Self::Clearcoat(coat) => {
+                coat.base.medium()
+            }
+            Self::ThinFilm(film) => {
+                film.base.medium()
+            }
+            Self::Sheen(sheen) => {
+                sheen.base.as_ref().and_then(|base| base.medium())
+            }
+            Self::Bumped(bumped) => {
+                bumped.base.medium()
+            }
+            Self::Cutout { base, .. } => {
+                base.medium()
+            }
+            _ => {
+                None
+            }
+        }
+    }
+
+    // Same dielectric with its index taken relative to the medium on the outside
+    pub fn with_outside_index(&self, outside_index: f64) -> Self {
+        let mut material: Self = self.clone();
+        match &mut material {
+            Self::Dielectric { refraction_index, .. }
+            | Self::RoughDielectric { refraction_index, .. }
+            | Self::Subsurface { refraction_index, .. } => {
+                *refraction_index /= outside_index;
+            }
+            Self::Mix(mix) => {
+                *mix = mix.with_outside_index(outside_index);
+            }
+            Self::Clearcoat(coat) => {
+                coat.base = Arc::new(coat.base.with_outside_index(outside_index));
+            }
+            Self::ThinFilm(film) => {
+                film.base = Arc::new(film.base.with_outside_index(outside_index));
+            }
+            Self::Sheen(sheen) => {
+                sheen.base = sheen.base.as_ref().map(|base| Arc::new(base.with_outside_index(outside_index)));
+            }
+            Self::Bumped(bumped) => {
+                bumped.base = Arc::new(bumped.base.with_outside_index(outside_index));
+            }
+            Self::Cutout { base, .. } => {
+                *base = Arc::new(base.with_outside_index(outside_index));
+            }
+            _ => {}
+        }
+        material
+    }
+
+    // Coverage of the surface, hits are skipped where it is zero
+    pub fn opacity(&self, rec: &HitRecord) -> f64 {
+        match self {
+            Self::Cutout { base, opacity } => {
+                opacity.sample(rec).x.clamp(0.0, 1.0) * base.opacity(rec)
+            }
+            Self::Mix(mix) => {
+                mix.opacity(rec)
+            }
+            Self::Clearcoat(coat) => {
+                coat.base.opacity(rec)
+            }
+            Self::ThinFilm(film) => {
+                film.base.opacity(rec)
+            }
+            Self::Sheen(sheen) => {
+                sheen.base.as_ref().map_or(1.0, |base| base.opacity(rec))
+            }
+            Self::Bumped(bumped) => {
+                bumped.base.opacity(rec)
+            }
+            _ => {
+                1.0
+            }
+        }
+    }
+
+    pub fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        match self {
+            Self::Light { emit, intensity, two_sided, profile } => {
+                if !rec.front_face && !two_sided {
+                    return Color::default();
+                }
+                let shape: f64 = match profile {
+                    Some(profile) => {
+                        let frame: Onb = Onb::build_from_w_and_tangent(&rec.normal, &rec.dpdu);
+                        profile.value_local(&frame.to_local(&-r.direction))
+                    }
+                    None => 1.0,
+                };
+                emit.sample(rec) * (*intensity * shape)
+            }
+            Self::Mix(mix) => {
+                mix.emitted(r, rec)
+            }
+            Self::Clearcoat(coat) => {
+                coat.base.emitted(r, rec)
+            }
+            Self::ThinFilm(film) => {
+                film.emitted(r, rec)
+            }
+            Self::Sheen(sheen) => {
+                sheen.emitted(r, rec)
+            }
+            Self::Bumped(bumped) => {
+                bumped.emitted(r, rec)
+            }
+            Self::Cutout { base, .. } => {
+                base.emitted(r, rec)
+            }
+            _ => {
+                Color::default()
+            }
+        }
+    }
+
+    // Luminous power of a light with this material over `area`, for choosing between lights:
+    // the radiance times cosine integrated over the hemisphere of each side (midpoint rule
+    // in cos theta and phi, so IES profiles count in every direction) at a grid of (u, v)
+    pub fn emitted_power(&self, area: f64) -> f64 {
+        const STEPS: usize = 4;
+        const THETA_STEPS: usize = 16;
+        const PHI_STEPS: usize = 32;
+        let mut power: f64 = 0.0;
+        for i in 0..STEPS {
+            for j in 0..STEPS {
+                for front_face in [true, false] {
+                    let side: f64 = if front_face { 1.0 } else { -1.0 };
+                    let rec: HitRecord = HitRecord { t: 0.0,
+                                                     u: (i as f64 + 0.5) / STEPS as f64,
+                                                     v: (j as f64 + 0.5) / STEPS as f64,
+                                                     p: Point3::default(),
+                                                     object_p: Point3::default(),
+                                                     normal: Vec3::new(0.0, side, 0.0),
+                                                     dpdu: Vec3::new(1.0, 0.0, 0.0),
+                                                     dpdv: Vec3::new(0.0, 0.0, 1.0),
+                                                     dndu: Vec3::default(),
+                                                     dndv: Vec3::default(),
+                                                     footprint: Footprint::default(),
+                                                     front_face,
+                                                     material: self };
+                    for k in 0..THETA_STEPS {
+                        let cos_theta: f64 = (k as f64 + 0.5) / THETA_STEPS as f64;
+                        let sin_theta: f64 = (1.0 - cos_theta * cos_theta).sqrt();
+                        for l in 0..PHI_STEPS {
+                            let phi: f64 = 2.0 * std::f64::consts::PI * (l as f64 + 0.5) / PHI_STEPS as f64;
+                            let outgoing: Vec3 = Vec3::new(sin_theta * phi.cos(), side * cos_theta, sin_theta * phi.sin());
+                            let r: Ray = Ray::new(&outgoing, &(-outgoing), 0.0);
+                            power += luminance(&self.emitted(&r, &rec)) * cos_theta;
+                        }
+                    }
+                }
+            }
+        }
+        2.0 * std::f64::consts::PI * area * power / (STEPS * STEPS * THETA_STEPS * PHI_STEPS) as f64
+    }
+
     pub fn new_lambertian(albedo: &Color) -> Self {
         Self::Lambertian { albedo: *albedo }
     }
 
     pub fn new_textured(texture: &Texture) -> Self {
-        Self::Textured { texture: *texture }
+        Self::Textured { texture: texture.clone() }
+    }
+
+    // Rough diffuse for clay, concrete or fabric, `sigma` is the slope deviation in degrees
+    pub fn new_oren_nayar(albedo: &Color, sigma: f64) -> Self {
+        Self::new_textured_oren_nayar(&Texture::new_solid(albedo), sigma)
     }
 
+    pub fn new_textured_oren_nayar(albedo: &Texture, sigma: f64) -> Self {
+        let sigma: f64 = sigma.to_radians();
+        let sigma2: f64 = sigma * sigma;
+        Self::OrenNayar { albedo: albedo.clone(),
+                          a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+                          b: 0.45 * sigma2 / (sigma2 + 0.09) }
+    }
+
+    // Mirror of constant reflectance `albedo`, blurred into a GGX lobe of alpha `fuzz`
     pub fn new_metal(albedo: &Color, fuzz: f64) -> Self {
         Self::Metal { albedo: *albedo, fuzz: fuzz }
     }
 
+    // GGX conductor with complex index of refraction eta + ik (per RGB channel)
+    pub fn new_conductor(eta: &Color, k: &Color, roughness: f64, anisotropy: f64) -> Self {
+        let (alpha_x, alpha_y): (f64, f64) = microfacet::roughness_to_alpha(roughness, anisotropy);
+        Self::Conductor { eta: *eta, k: *k, alpha_x, alpha_y }
+    }
+
+    pub fn new_gold(roughness: f64) -> Self {
+        Self::new_conductor(&Color::new(0.143, 0.374, 1.442), &Color::new(3.983, 2.385, 1.603), roughness, 0.0)
+    }
+
+    pub fn new_copper(roughness: f64) -> Self {
+        Self::new_conductor(&Color::new(0.200, 0.924, 1.102), &Color::new(3.912, 2.452, 2.142), roughness, 0.0)
+    }
+
+    pub fn new_aluminium(roughness: f64) -> Self {
+        Self::new_conductor(&Color::new(1.657, 0.880, 0.521), &Color::new(9.224, 6.270, 4.837), roughness, 0.0)
+    }
+
     pub fn new_dielectric(refraction_index: f64) -> Self {
-        Self::Dielectric { refraction_index: refraction_index }
+        Self::Dielectric { refraction_index, absorption: Color::default(), priority: 0 }
+    }
+
+    // Frosted glass, falls back to `Dielectric` for roughness close to zero
+    pub fn new_rough_dielectric(refraction_index: f64, roughness: f64) -> Self {
+        let (alpha_x, alpha_y): (f64, f64) = microfacet::roughness_to_alpha(roughness, 0.0);
+        Self::RoughDielectric { refraction_index, alpha_x, alpha_y, absorption: Color::default(), priority: 0 }
+    }
+
+    // Colored glass or liquid: light travelling `tint_distance` inside is filtered to `tint`.
+    // Where dielectrics overlap, the one with the highest `priority` wins.
+    pub fn new_absorbing_dielectric(refraction_index: f64, roughness: f64, tint: &Color, tint_distance: f64, priority: u32) -> Self {
+        let absorption: Color = Color::new(-tint.x.max(1e-6).ln(), -tint.y.max(1e-6).ln(), -tint.z.max(1e-6).ln()) / tint_distance;
+        match Self::new_rough_dielectric(refraction_index, roughness) {
+            Self::RoughDielectric { alpha_x, alpha_y, .. } if !microfacet::is_smooth(alpha_x, alpha_y) => {
+                Self::RoughDielectric { refraction_index, alpha_x, alpha_y, absorption, priority }
+            }
+            _ => {
+                Self::Dielectric { refraction_index, absorption, priority }
+            }
+        }
+    }
+
+    // Random walk subsurface scattering for closed objects (skin, wax, marble, milk).
+    // `albedo` is the single scattering albedo of the interior and `mean_free_path`
+    // the average distance travelled between scattering events, per channel.
+    pub fn new_subsurface(albedo: &Color, mean_free_path: &Color, refraction_index: f64, roughness: f64) -> Self {
+        let extinction: Color = Color::new(1.0 / mean_free_path.x, 1.0 / mean_free_path.y, 1.0 / mean_free_path.z);
+        let scattering: Color = *albedo * extinction;
+        let (alpha, _): (f64, f64) = microfacet::roughness_to_alpha(roughness, 0.0);
+        Self::Subsurface { refraction_index, alpha, absorption: extinction - scattering, scattering }
+    }
+
+    pub fn new_principled(principled: &Principled) -> Self {
+        Self::Principled(Box::new(principled.clone()))
+    }
+
+    // Blend `first` and `second`, `weight` = 1 is fully `second`
+    pub fn new_mix(first: &Material, second: &Material, weight: &Texture) -> Self {
+        Self::Mix(Mix::new(first, second, weight))
+    }
+
+    pub fn new_clearcoat(base: &Material, refraction_index: f64, roughness: f64) -> Self {
+        Self::Clearcoat(Clearcoat::new(base, refraction_index, roughness))
+    }
+
+    // Interference coating over a dielectric or metal base, e.g. a soap bubble is a
+    // film of index 1.33 over `new_dielectric(1.0)`. `thickness` in nanometres.
+    pub fn new_thin_film(base: &Material, thickness: &Texture, film_index: f64) -> Self {
+        Self::ThinFilm(ThinFilm::new(base, thickness, film_index))
+    }
+
+    // Velvet: only the grazing angle sheen lobe, tinted by `tint`
+    pub fn new_velvet(tint: &Texture, roughness: f64) -> Self {
+        Self::Sheen(Sheen::new(None, tint, roughness))
+    }
+
+    // Fabric sheen layered over any material
+    pub fn new_sheen(base: &Material, tint: &Texture, roughness: f64) -> Self {
+        Self::Sheen(Sheen::new(Some(base), tint, roughness))
+    }
+
+    // Shades `base` with normals from a tangent space normal map
+    pub fn new_normal_mapped(base: &Material, map: &Texture, strength: f64) -> Self {
+        Self::Bumped(Bumped::new(base, Perturbation::NormalMap { map: map.clone(), strength }))
+    }
+
+    // Shades `base` as if displaced by `height` (red channel) times `scale`
+    pub fn new_bump_mapped(base: &Material, height: &Texture, scale: f64) -> Self {
+        Self::Bumped(Bumped::new(base, Perturbation::BumpMap { height: height.clone(), scale }))
+    }
+
+    // Alpha masked `base` for leaves, fences and decals. `opacity` is read from the red channel.
+    pub fn new_cutout(base: &Material, opacity: &Texture) -> Self {
+        Self::Cutout { base: Arc::new(base.clone()), opacity: opacity.clone() }
     }
 
     pub fn new_light(albedo: &Color) -> Self {
-        Self::Light { albedo: *albedo }
+        Self::Light { emit: Texture::new_solid(albedo), intensity: 1.0, two_sided: true, profile: None }
+    }
+
+    // Emission given by a texture (e.g. a screen or a lamp shade), scaled by `intensity`.
+    // One-sided lights only emit on the side the surface normal points to.
+    pub fn new_textured_light(emit: &Texture, intensity: f64, two_sided: bool) -> Self {
+        Self::Light { emit: emit.clone(), intensity, two_sided, profile: None }
+    }
+
+    // Uniform light emitting a total of `power` watts from a surface of `area`
+    pub fn new_light_power(color: &Color, power: f64, area: f64, two_sided: bool) -> Self {
+        let sides: f64 = if two_sided { 2.0 } else { 1.0 };
+        let radiance: f64 = power / (std::f64::consts::PI * area * sides);
+        Self::Light { emit: Texture::new_solid(color), intensity: radiance, two_sided, profile: None }
+    }
+
+    // Black body color at `kelvin` (e.g. 2700 for tungsten, 6500 for daylight), of luminance `intensity`
+    pub fn new_blackbody_light(kelvin: f64, intensity: f64, two_sided: bool) -> Self {
+        Self::Light { emit: Texture::new_solid(&spectrum::blackbody(kelvin)), intensity, two_sided, profile: None }
+    }
+
+    // Emitter whose radiance follows an IES profile, `color` * `intensity` where it is brightest
+    pub fn new_photometric_light(color: &Color, intensity: f64, two_sided: bool, profile: &IesProfile) -> Self {
+        Self::Light { emit: Texture::new_solid(color), intensity, two_sided, profile: Some(Arc::new(profile.clone())) }
     }
 }
 
@@ -123,4 +745,33 @@ impl Default for Material { // Stupid bodge
     fn default() -> Self {
         Self::Lambertian { albedo: Color::default() }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearcoat_keeps_the_base_medium() {
+        let coated: Material = Material::new_clearcoat(&Material::new_dielectric(1.5), 1.6, 0.0);
+        assert_eq!(coated.medium(), Material::new_dielectric(1.5).medium());
+        assert_eq!(coated.with_outside_index(1.5).medium().map(|medium| medium.refraction_index), Some(1.0));
+    }
+
+    #[test]
+    fn mix_uses_the_transmitting_side() {
+        let weight: Texture = Texture::new_scalar(0.5);
+        let glass: Material = Material::new_dielectric(1.5);
+        let paint: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+        assert_eq!(Material::new_mix(&paint, &glass, &weight).medium(), glass.medium());
+        assert_eq!(Material::new_mix(&glass, &glass, &weight).medium(), glass.medium());
+        assert_eq!(Material::new_mix(&glass, &Material::new_dielectric(1.3), &weight).medium(), None);
+        assert_eq!(Material::new_mix(&paint, &paint, &weight).medium(), None);
+    }
+
+    #[test]
+    fn sheen_keeps_the_base_medium() {
+        let velvet: Material = Material::new_sheen(&Material::new_dielectric(1.5), &Texture::new_scalar(1.0), 0.5);
+        assert_eq!(velvet.medium(), Material::new_dielectric(1.5).medium());
+        assert_eq!(velvet.with_outside_index(1.5).medium().map(|medium| medium.refraction_index), Some(1.0));
+    }
+}
@@ -0,0 +1,131 @@
+use rand::Rng;
+
+use crate::vec3::Color;
+use crate::PRNG;
+
+// Interior of a dielectric. Overlapping dielectrics are resolved by priority
+// (Schmidt and Budge 2002): inside a medium, surfaces of lower priority media
+// are ignored, so e.g. a liquid can slightly overlap the glass holding it.
+// Media with a non-zero `scattering` coefficient are walked through by the
+// integrator, which is how subsurface scattering is rendered.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Medium {
+    pub refraction_index: f64,
+    pub absorption: Color,
+    pub scattering: Color,
+    pub priority: u32,
+}
+
+const MAX_MEDIA: usize = 8;
+
+// Media a path is currently inside of, carried along by the integrator
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MediumStack {
+    media: [Medium; MAX_MEDIA],
+    len: usize,
+}
+
+impl Medium {
+    pub fn new(refraction_index: f64, absorption: &Color, scattering: &Color, priority: u32) -> Self {
+        Self { refraction_index, absorption: *absorption, scattering: *scattering, priority }
+    }
+
+    fn extinction(&self) -> Color {
+        self.absorption + self.scattering
+    }
+
+    // Beer-Lambert attenuation over `distance`
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let extinction: Color = self.extinction();
+        Color::new((-extinction.x * distance).exp(),
+                   (-extinction.y * distance).exp(),
+                   (-extinction.z * distance).exp())
+    }
+
+    // Samples a free flight distance, returning where the path scatters if that
+    // happens before `distance`, and the path weight. The distance is sampled from
+    // a randomly chosen channel and weighted by the average pdf of all channels.
+    pub fn sample_collision(&self, distance: f64, rng: &mut PRNG) -> (Option<f64>, Color) {
+        if self.scattering == Color::default() {
+            return (None, self.transmittance(distance));
+        }
+        let extinction: Color = self.extinction();
+        let sigma: f64 = match rng.gen_range(0..3) {
+            0 => extinction.x,
+            1 => extinction.y,
+            _ => extinction.z,
+        };
+        let t: f64 = -(1.0 - rng.gen::<f64>()).ln() / sigma;
+        if t < distance {
+            let transmittance: Color = self.transmittance(t);
+            let density: Color = extinction * transmittance;
+            let pdf: f64 = (density.x + density.y + density.z) / 3.0;
+            if pdf <= 0.0 {
+                return (None, Color::default());
+            }
+            (Some(t), self.scattering * transmittance / pdf)
+        } else {
+            let transmittance: Color = self.transmittance(distance);
+            let pdf: f64 = (transmittance.x + transmittance.y + transmittance.z) / 3.0;
+            if pdf <= 0.0 {
+                return (None, Color::default());
+            }
+            (None, transmittance / pdf)
+        }
+    }
+}
+
+impl MediumStack {
+    // Highest priority medium, the most recently entered one on ties
+    pub fn current(&self) -> Option<&Medium> {
+        self.media[..self.len].iter().max_by_key(|medium| medium.priority)
+    }
+
+    // Index of refraction surrounding the path, 1.0 for vacuum
+    pub fn refraction_index(&self) -> f64 {
+        match self.current() {
+            Some(medium) => medium.refraction_index,
+            None => 1.0,
+        }
+    }
+
+    pub fn transmittance(&self, distance: f64) -> Color {
+        match self.current() {
+            Some(medium) => medium.transmittance(distance),
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn sample_collision(&self, distance: f64, rng: &mut PRNG) -> (Option<f64>, Color) {
+        match self.current() {
+            Some(medium) => medium.sample_collision(distance, rng),
+            None => (None, Color::new(1.0, 1.0, 1.0)),
+        }
+    }
+
+    // Whether the boundary of `medium` is a real interface or has to be skipped
+    pub fn is_interface(&self, medium: &Medium) -> bool {
+        match self.current() {
+            Some(current) => medium.priority >= current.priority,
+            None => true,
+        }
+    }
+
+    pub fn entered(&self, medium: &Medium) -> Self {
+        let mut stack: Self = *self;
+        if stack.len < MAX_MEDIA {
+            stack.media[stack.len] = *medium;
+            stack.len += 1;
+        }
+        stack
+    }
+
+    pub fn exited(&self, medium: &Medium) -> Self {
+        let mut stack: Self = *self;
+        if let Some(i) = stack.media[..stack.len].iter().rposition(|m| m == medium) {
+            stack.media.copy_within(i + 1..stack.len, i);
+            stack.len -= 1;
+        }
+        stack
+    }
+}
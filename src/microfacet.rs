@@ -0,0 +1,112 @@
+use crate::vec3::Vec3;
+
+// Anisotropic GGX (Trowbridge-Reitz) distribution. All directions are in the
+// local shading frame, with the normal along +z.
+
+// Below this alpha the lobe is treated as a perfect mirror
+pub const MIN_ALPHA: f64 = 1e-3;
+
+// Disney style remapping of perceptual roughness/anisotropy to (alpha_x, alpha_y).
+// Positive anisotropy stretches the highlight along dp/du, `anisotropy` is clamped to [-1, 1].
+pub fn roughness_to_alpha(roughness: f64, anisotropy: f64) -> (f64, f64) {
+    let alpha: f64 = roughness * roughness;
+    let aspect: f64 = (1.0 - 0.9 * anisotropy.clamp(-1.0, 1.0)).sqrt();
+    (alpha / aspect, alpha * aspect)
+}
+
+pub fn is_smooth(alpha_x: f64, alpha_y: f64) -> bool {
+    alpha_x.max(alpha_y) < MIN_ALPHA
+}
+
+pub fn distribution(h: &Vec3, alpha_x: f64, alpha_y: f64) -> f64 {
+    if h.z <= 0.0 {
+        return 0.0;
+    }
+    let d: f64 = (h.x * h.x) / (alpha_x * alpha_x) + (h.y * h.y) / (alpha_y * alpha_y) + h.z * h.z;
+    1.0 / (std::f64::consts::PI * alpha_x * alpha_y * d * d)
+}
+
+fn lambda(w: &Vec3, alpha_x: f64, alpha_y: f64) -> f64 {
+    if w.z == 0.0 {
+        return f64::INFINITY;
+    }
+    let a2_tan2: f64 = (alpha_x * alpha_x * w.x * w.x + alpha_y * alpha_y * w.y * w.y) / (w.z * w.z);
+    ((1.0 + a2_tan2).sqrt() - 1.0) / 2.0
+}
+
+// Smith masking for one direction
+pub fn smith_g1(w: &Vec3, alpha_x: f64, alpha_y: f64) -> f64 {
+    1.0 / (1.0 + lambda(w, alpha_x, alpha_y))
+}
+
+// Height-correlated Smith masking-shadowing
+pub fn smith_g2(wo: &Vec3, wi: &Vec3, alpha_x: f64, alpha_y: f64) -> f64 {
+    1.0 / (1.0 + lambda(wo, alpha_x, alpha_y) + lambda(wi, alpha_x, alpha_y))
+}
+
+// Heitz 2018, "Sampling the GGX Distribution of Visible Normals"
+pub fn sample_visible_normal(wo: &Vec3, alpha_x: f64, alpha_y: f64, u1: f64, u2: f64) -> Vec3 {
+    let vh: Vec3 = Vec3::new(alpha_x * wo.x, alpha_y * wo.y, wo.z).normalize();
+    let lensq: f64 = vh.x * vh.x + vh.y * vh.y;
+    let t1: Vec3 = if lensq > 0.0 { Vec3::new(-vh.y, vh.x, 0.0) / lensq.sqrt() } else { Vec3::new(1.0, 0.0, 0.0) };
+    let t2: Vec3 = vh.cross(&t1);
+
+    let r: f64 = u1.sqrt();
+    let phi: f64 = 2.0 * std::f64::consts::PI * u2;
+    let p1: f64 = r * phi.cos();
+    let s: f64 = 0.5 * (1.0 + vh.z);
+    let p2: f64 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+    let nh: Vec3 = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+    Vec3::new(alpha_x * nh.x, alpha_y * nh.y, nh.z.max(0.0)).normalize()
+}
+
+// Density of `sample_visible_normal` returning `h`
+pub fn visible_normal_pdf(wo: &Vec3, h: &Vec3, alpha_x: f64, alpha_y: f64) -> f64 {
+    if wo.z <= 0.0 {
+        return 0.0;
+    }
+    smith_g1(wo, alpha_x, alpha_y) * wo.dot(h).max(0.0) * distribution(h, alpha_x, alpha_y) / wo.z
+}
+
+// Exact unpolarized Fresnel reflectance of a dielectric interface, eta = eta_t / eta_i.
+// Returns 1 on total internal reflection.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i: f64 = cos_i.clamp(0.0, 1.0);
+    let sin2_t: f64 = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t: f64 = (1.0 - sin2_t).sqrt();
+    let r_parallel: f64 = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular: f64 = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+// Exact Fresnel reflectance of a conductor with complex index eta + ik, per channel
+pub fn fresnel_conductor(cos_i: f64, eta: &Vec3, k: &Vec3) -> Vec3 {
+    Vec3::new(fresnel_conductor_channel(cos_i, eta.x, k.x),
+              fresnel_conductor_channel(cos_i, eta.y, k.y),
+              fresnel_conductor_channel(cos_i, eta.z, k.z))
+}
+
+fn fresnel_conductor_channel(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_i: f64 = cos_i.clamp(0.0, 1.0);
+    let cos2: f64 = cos_i * cos_i;
+    let sin2: f64 = 1.0 - cos2;
+    let eta2: f64 = eta * eta;
+    let k2: f64 = k * k;
+
+    let t0: f64 = eta2 - k2 - sin2;
+    let a2_plus_b2: f64 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1: f64 = a2_plus_b2 + cos2;
+    let a: f64 = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2: f64 = 2.0 * cos_i * a;
+    let rs: f64 = (t1 - t2) / (t1 + t2);
+
+    let t3: f64 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4: f64 = t2 * sin2;
+    let rp: f64 = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
@@ -1,8 +1,9 @@
 use crate::vec3::{Vec3, Point3};
 use crate::material::Material;
+use crate::texture::Footprint;
 use crate::hittable::{Hittable, HitRecord};
 use crate::ray::Ray;
-use crate::sphere::get_sphere_uv;
+use crate::sphere::{get_sphere_uv, get_sphere_tangents};
 
 pub struct MovingSphere {
     pub center0: Point3,
@@ -20,7 +21,7 @@ impl MovingSphere {
                time0: time0,
                time1: time1,
                radius: radius,
-               material: *material }
+               material: material.clone() }
     }
 
     pub fn center(&self, time: f64) -> Point3 {
@@ -47,13 +48,22 @@ impl Hittable for MovingSphere {
                     let normal = (p - self.center(r.time)) / self.radius;
                     let front_face = r.direction.dot(&normal) < 0.0;
                     let (u, v) = get_sphere_uv(&normal);
+                    let (dpdu, dpdv) = get_sphere_tangents(&normal, self.radius);
 
                     return Some(HitRecord {
                         t: *root,
                         u: u,
                         v: v,
                         p: p,
+                        // Where the point was at time0, so textures move with the sphere
+                        object_p: p - self.center(r.time) + self.center0,
                         normal: if front_face { normal } else { -normal },
+                        dpdu,
+                        dpdv,
+                        // n = (p - center) / radius, flipped with the normal on the inside
+                        dndu: if front_face { dpdu / self.radius } else { -dpdu / self.radius },
+                        dndv: if front_face { dpdv / self.radius } else { -dpdv / self.radius },
+                        footprint: Footprint::default(),
                         front_face: front_face,
                         material: &self.material,
                     });
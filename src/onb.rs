@@ -0,0 +1,41 @@
+use crate::vec3::Vec3;
+
+// Orthonormal basis around a normal, used to sample directions in a local frame
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn build_from_w(n: &Vec3) -> Self {
+        let w: Vec3 = n.normalize();
+        let a: Vec3 = if w.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let v: Vec3 = w.cross(&a).normalize();
+        let u: Vec3 = w.cross(&v);
+        Self { u, v, w }
+    }
+
+    // Basis with `u` along `tangent` projected onto the plane of `n`, so anisotropic
+    // lobes follow the surface parameterization. Falls back to `build_from_w` if they're parallel.
+    pub fn build_from_w_and_tangent(n: &Vec3, tangent: &Vec3) -> Self {
+        let w: Vec3 = n.normalize();
+        let t: Vec3 = *tangent - w * w.dot(tangent);
+        if t.sqlen() < 1e-12 {
+            return Self::build_from_w(&w);
+        }
+        let u: Vec3 = t.normalize();
+        Self { u, v: w.cross(&u), w }
+    }
+
+    // Local coordinates -> world
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        self.u * a.x + self.v * a.y + self.w * a.z
+    }
+
+    // World coordinates -> local
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
+}
@@ -0,0 +1,96 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rand_xoshiro::rand_core::SeedableRng;
+
+use crate::vec3::{Vec3, Point3};
+use crate::PRNG;
+
+const POINT_COUNT: usize = 256;
+
+// Gradient noise from the second book: random unit vectors on the lattice,
+// trilinearly interpolated with Hermite smoothing
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    ranvec: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    // The same seed always gives the same noise
+    pub fn new(seed: u64) -> Self {
+        let mut rng: PRNG = PRNG::seed_from_u64(seed);
+        let ranvec: Vec<Vec3> = (0..POINT_COUNT).map(|_| {
+            Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)).normalize()
+        }).collect();
+        let perm_x: Vec<usize> = Self::generate_perm(&mut rng);
+        let perm_y: Vec<usize> = Self::generate_perm(&mut rng);
+        let perm_z: Vec<usize> = Self::generate_perm(&mut rng);
+        Self { ranvec, perm_x, perm_y, perm_z }
+    }
+
+    fn generate_perm(rng: &mut PRNG) -> Vec<usize> {
+        let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+        perm.shuffle(rng);
+        perm
+    }
+
+    // Noise in [-1, 1]
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let u: f64 = p.x - p.x.floor();
+        let v: f64 = p.y - p.y.floor();
+        let w: f64 = p.z - p.z.floor();
+        let i: i64 = p.x.floor() as i64;
+        let j: i64 = p.y.floor() as i64;
+        let k: i64 = p.z.floor() as i64;
+
+        let mut c: [[[Vec3; 2]; 2]; 2] = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    let x: usize = self.perm_x[((i + di as i64) & 255) as usize];
+                    let y: usize = self.perm_y[((j + dj as i64) & 255) as usize];
+                    let z: usize = self.perm_z[((k + dk as i64) & 255) as usize];
+                    *corner = self.ranvec[x ^ y ^ z];
+                }
+            }
+        }
+        Self::perlin_interp(&c, u, v, w)
+    }
+
+    fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        // Hermite cubic to round off the lattice
+        let uu: f64 = u * u * (3.0 - 2.0 * u);
+        let vv: f64 = v * v * (3.0 - 2.0 * v);
+        let ww: f64 = w * w * (3.0 - 2.0 * w);
+
+        let mut accum: f64 = 0.0;
+        for (i, plane) in c.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, corner) in row.iter().enumerate() {
+                    let (fi, fj, fk): (f64, f64, f64) = (i as f64, j as f64, k as f64);
+                    let weight: Vec3 = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * corner.dot(&weight);
+                }
+            }
+        }
+        accum
+    }
+
+    // Absolute value of `depth` octaves of noise, each at twice the frequency and half the weight
+    pub fn turb(&self, p: &Point3, depth: u32) -> f64 {
+        let mut accum: f64 = 0.0;
+        let mut temp_p: Point3 = *p;
+        let mut weight: f64 = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p = temp_p * 2.0;
+        }
+        accum.abs()
+    }
+}
@@ -0,0 +1,187 @@
+use rand::prelude::Rng;
+
+use crate::vec3::{Vec3, Color};
+use crate::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+use crate::material::{self, ScatterRecord};
+use crate::microfacet;
+use crate::onb::Onb;
+use crate::PRNG;
+
+// Disney style principled bsdf (Burley 2012/2015). Every input is a texture,
+// scalar inputs read the red channel. Maps directly onto glTF metallic-roughness
+// materials: base color, metallic and roughness, plus the KHR extensions.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    pub base_color: Texture,
+    pub metallic: Texture,
+    pub roughness: Texture,
+    pub specular: Texture,
+    pub sheen: Texture,
+    pub clearcoat: Texture,
+    pub clearcoat_gloss: Texture,
+    pub transmission: Texture,
+    pub refraction_index: f64,
+}
+
+// Inputs evaluated at a hit point
+struct Lobes {
+    base_color: Color,
+    metallic: f64,
+    alpha: f64,
+    specular: f64,
+    sheen: f64,
+    clearcoat: f64,
+    clearcoat_alpha: f64,
+    transmission: f64,
+}
+
+fn schlick_weight(cosine: f64) -> f64 {
+    (1.0 - cosine.clamp(0.0, 1.0)).powi(5)
+}
+
+impl Lobes {
+    // Selection probabilities of the diffuse, specular, clearcoat and transmission lobes
+    fn weights(&self) -> [f64; 4] {
+        let dielectric: f64 = 1.0 - self.metallic;
+        let weights: [f64; 4] = [dielectric * (1.0 - self.transmission),
+                                 1.0 - dielectric * self.transmission,
+                                 0.25 * self.clearcoat,
+                                 dielectric * self.transmission];
+        let total: f64 = weights.iter().sum();
+        weights.map(|w| w / total)
+    }
+
+    fn specular_color(&self) -> Color {
+        let dielectric: Color = Color::new(1.0, 1.0, 1.0) * (0.08 * self.specular);
+        dielectric * (1.0 - self.metallic) + self.base_color * self.metallic
+    }
+}
+
+impl Principled {
+    // Rough white plastic with the given base color
+    pub fn new(base_color: &Texture) -> Self {
+        Self { base_color: base_color.clone(),
+               metallic: Texture::new_scalar(0.0),
+               roughness: Texture::new_scalar(0.5),
+               specular: Texture::new_scalar(0.5),
+               sheen: Texture::new_scalar(0.0),
+               clearcoat: Texture::new_scalar(0.0),
+               clearcoat_gloss: Texture::new_scalar(1.0),
+               transmission: Texture::new_scalar(0.0),
+               refraction_index: 1.5 }
+    }
+
+    fn lobes(&self, rec: &HitRecord) -> Lobes {
+        let scalar = |texture: &Texture| texture.sample(rec).x.clamp(0.0, 1.0);
+        let roughness: f64 = scalar(&self.roughness);
+        let clearcoat_gloss: f64 = scalar(&self.clearcoat_gloss);
+        Lobes { base_color: self.base_color.sample(rec),
+                metallic: scalar(&self.metallic),
+                alpha: (roughness * roughness).max(microfacet::MIN_ALPHA),
+                specular: scalar(&self.specular),
+                sheen: scalar(&self.sheen),
+                clearcoat: scalar(&self.clearcoat),
+                clearcoat_alpha: (0.1 * (1.0 - clearcoat_gloss) + 0.001 * clearcoat_gloss).max(microfacet::MIN_ALPHA),
+                transmission: scalar(&self.transmission) }
+    }
+
+    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<ScatterRecord> {
+        let lobes: Lobes = self.lobes(rec);
+        let (uvw, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let weights: [f64; 4] = lobes.weights();
+        let mut choice: f64 = rng.gen();
+        let mut lobe: usize = 0;
+        while lobe < 3 && choice >= weights[lobe] {
+            choice -= weights[lobe];
+            lobe += 1;
+        }
+        let direction: Vec3 = match lobe {
+            0 => uvw.local(&Vec3::random_cosine_direction(rng)),
+            1 | 2 => {
+                let alpha: f64 = if lobe == 1 { lobes.alpha } else { lobes.clearcoat_alpha };
+                let h: Vec3 = microfacet::sample_visible_normal(&wo, alpha, alpha, rng.gen(), rng.gen());
+                uvw.local(&material::reflect(&(-wo), &h))
+            }
+            _ => material::scatter_rough_dielectric(r, rec, self.refraction_index, lobes.alpha, lobes.alpha, rng)?.scattered.direction,
+        };
+
+        // One-sample MIS over all lobes: weight by the combined pdf
+        let pdf: f64 = self.lobes_pdf(&lobes, r, rec, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord { scattered: Ray::new(&rec.p, &direction, r.time),
+                             attenuation: self.lobes_eval(&lobes, r, rec, &direction) / pdf,
+                             pdf: Some(pdf) })
+    }
+
+    pub fn eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.lobes_eval(&self.lobes(rec), r, rec, direction)
+    }
+
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.lobes_pdf(&self.lobes(rec), r, rec, direction)
+    }
+
+    fn lobes_eval(&self, lobes: &Lobes, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let (uvw, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        let wi: Vec3 = uvw.to_local(&direction.normalize());
+        let dielectric: f64 = 1.0 - lobes.metallic;
+        let mut value: Color = Color::default();
+
+        if lobes.transmission > 0.0 && dielectric > 0.0 {
+            let transmitted: Color = material::eval_rough_dielectric(r, rec, direction, self.refraction_index, lobes.alpha, lobes.alpha);
+            value = value + lobes.base_color * transmitted * (dielectric * lobes.transmission);
+        }
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return value;
+        }
+        let h: Vec3 = (wo + wi).normalize();
+        let cos_d: f64 = wi.dot(&h);
+
+        // Burley diffuse with retro-reflection, plus sheen at grazing angles
+        let fd90: f64 = 0.5 + 2.0 * lobes.alpha.sqrt() * cos_d * cos_d;
+        let diffuse: f64 = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z)) * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z));
+        let sheen: f64 = lobes.sheen * schlick_weight(cos_d);
+        value = value + (lobes.base_color * (diffuse / std::f64::consts::PI) + Color::new(sheen, sheen, sheen))
+            * (dielectric * (1.0 - lobes.transmission) * wi.z);
+
+        let f0: Color = lobes.specular_color();
+        let fresnel: Color = f0 + (Color::new(1.0, 1.0, 1.0) - f0) * schlick_weight(cos_d);
+        let specular: f64 = microfacet::distribution(&h, lobes.alpha, lobes.alpha) * microfacet::smith_g2(&wo, &wi, lobes.alpha, lobes.alpha) / (4.0 * wo.z);
+        value = value + fresnel * (specular * (1.0 - dielectric * lobes.transmission));
+
+        if lobes.clearcoat > 0.0 {
+            let fresnel: f64 = 0.04 + 0.96 * schlick_weight(cos_d);
+            let a: f64 = lobes.clearcoat_alpha;
+            let clearcoat: f64 = fresnel * microfacet::distribution(&h, a, a) * microfacet::smith_g2(&wo, &wi, a, a) / (4.0 * wo.z);
+            value = value + Color::new(1.0, 1.0, 1.0) * (0.25 * lobes.clearcoat * clearcoat);
+        }
+        value
+    }
+
+    fn lobes_pdf(&self, lobes: &Lobes, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let (uvw, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        let wi: Vec3 = uvw.to_local(&direction.normalize());
+        let weights: [f64; 4] = lobes.weights();
+        let mut pdf: f64 = 0.0;
+
+        if weights[3] > 0.0 {
+            pdf += weights[3] * material::pdf_rough_dielectric(r, rec, direction, self.refraction_index, lobes.alpha, lobes.alpha);
+        }
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return pdf;
+        }
+        let h: Vec3 = (wo + wi).normalize();
+        let reflection: f64 = 4.0 * wo.dot(&h);
+        pdf += weights[0] * wi.z / std::f64::consts::PI;
+        pdf += weights[1] * microfacet::visible_normal_pdf(&wo, &h, lobes.alpha, lobes.alpha) / reflection;
+        pdf += weights[2] * microfacet::visible_normal_pdf(&wo, &h, lobes.clearcoat_alpha, lobes.clearcoat_alpha) / reflection;
+        pdf
+    }
+}
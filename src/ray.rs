@@ -1,15 +1,26 @@
 use crate::vec3::{Vec3, Point3};
 
+// Rays through the neighbouring pixels in x and y, tracking how wide a
+// pixel's footprint is for texture filtering
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RayDifferential {
+    pub rx_origin: Point3,
+    pub rx_direction: Vec3,
+    pub ry_origin: Point3,
+    pub ry_direction: Vec3,
+}
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Ray {
     pub origin: Point3,
     pub direction: Vec3,
     pub time: f64,
+    pub differential: Option<RayDifferential>,
 }
 
 impl Ray {
     pub fn new(o: &Vec3, d: &Vec3, t: f64) -> Self {
-        Ray { origin: *o, direction: *d, time: t }
+        Ray { origin: *o, direction: *d, time: t, differential: None }
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
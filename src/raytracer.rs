@@ -8,10 +8,17 @@ use image::ImageEncoder;
 use rand_xoshiro::rand_core::SeedableRng;
 use rand_xoshiro::Xoroshiro128Plus;
 
+use crate::background::Background;
 use crate::camera::Camera;
-use crate::hittable::Hittable;
-use crate::ray::Ray;
-use crate::vec3::{Vec3, Color};
+use crate::delta_light::{DeltaLight, LightSample};
+use crate::hittable::{Hittable, HitRecord};
+use crate::hittable_list::HittableList;
+use crate::light_tree::LightTree;
+use crate::material::Material;
+use crate::medium::{Medium, MediumStack};
+use crate::ray::{Ray, RayDifferential};
+use crate::texture::Footprint;
+use crate::vec3::{Vec3, Point3, Color};
 use crate::PRNG;
 
 fn write_image(filename: &str, 
@@ -30,44 +37,271 @@ fn clamp(x: f64, min: f64, max: f64) -> f64 {
     return x;
 }
 
-fn ray_color(r: &Ray, background: Color, world: &Box<dyn Hittable>, depth: i32, rng: &mut PRNG) -> Color {
+// MIS weights for combining light and bsdf sampling (Veach's heuristics)
+pub fn balance_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    if f_pdf + g_pdf <= 0.0 { return 0.0; }
+    f_pdf / (f_pdf + g_pdf)
+}
+
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let f2: f64 = f_pdf * f_pdf;
+    let g2: f64 = g_pdf * g_pdf;
+    if f2 + g2 <= 0.0 { return 0.0; }
+    f2 / (f2 + g2)
+}
+
+// What gets rendered: the geometry, the emitters in it to sample for direct lighting,
+// the delta lights and what rays that leave the scene see
+pub struct Scene {
+    pub world: Box<dyn Hittable>,
+    pub lights: HittableList,
+    pub delta_lights: Vec<DeltaLight>,
+    pub background: Background,
+}
+
+impl Scene {
+    pub fn new(world: Box<dyn Hittable>, lights: HittableList, delta_lights: Vec<DeltaLight>, background: Background) -> Self {
+        Self { world, lights, delta_lights, background }
+    }
+}
+
+// The scene as the integrator sees it, with the lights arranged in a tree for sampling
+struct SceneView<'a> {
+    world: &'a dyn Hittable,
+    lights: LightTree<'a>,
+    delta_lights: &'a [DeltaLight],
+    background: &'a Background,
+}
+
+// Probability of sampling the lights list rather than the background
+fn lights_probability(lights: &LightTree, background: &Background) -> f64 {
+    match (lights.is_empty(), background.is_light()) {
+        (false, true) => 0.5,
+        (false, false) => 1.0,
+        (true, _) => 0.0,
+    }
+}
+
+// Solid angle density of the light sampling strategy for a ray leaving `origin`
+fn light_sampling_pdf(origin: &Point3, direction: &Vec3, lights: &LightTree, background: &Background) -> f64 {
+    let p_lights: f64 = lights_probability(lights, background);
+    let mut pdf: f64 = 0.0;
+    if p_lights > 0.0 {
+        pdf += p_lights * lights.pdf_value(origin, direction);
+    }
+    if background.is_light() {
+        pdf += (1.0 - p_lights) * background.pdf_value(direction);
+    }
+    pdf
+}
+
+// Direct lighting at `rec` by sampling a point on the lights or a direction of the background
+fn sample_lights(r: &Ray, rec: &HitRecord, scene: &SceneView, media: &MediumStack, rng: &mut PRNG) -> Color {
+    let (lights, background): (&LightTree, &Background) = (&scene.lights, scene.background);
+    if lights.is_empty() && !background.is_light() {
+        return Color::default();
+    }
+    let direction: Vec3 = if rng.gen::<f64>() < lights_probability(lights, background) {
+        lights.random(&rec.p, rng)
+    } else {
+        background.random(rng)
+    };
+    let light_pdf: f64 = light_sampling_pdf(&rec.p, &direction, lights, background);
+    if light_pdf <= 0.0 {
+        return Color::default();
+    }
+    let f: Color = rec.material.eval(r, rec, &direction);
+    if f.near_zero() {
+        return Color::default();
+    }
+    let bsdf_pdf: f64 = rec.material.pdf(r, rec, &direction);
+    let weight: f64 = power_heuristic(light_pdf, bsdf_pdf);
+    let shadow_ray: Ray = Ray::new(&rec.p, &direction, r.time);
+    match scene.world.hit(&shadow_ray, 0.001, f64::INFINITY) {
+        Some(light_rec) => {
+            let transmittance: Color = media.transmittance(light_rec.t * direction.len());
+            f * transmittance * light_rec.material.emitted(&shadow_ray, &light_rec) * (weight / light_pdf)
+        }
+        None => {
+            f * background.value(&direction) * (weight / light_pdf)
+        }
+    }
+}
+
+// Direct lighting at `rec` from every delta light, each reached with one shadow ray.
+// The light has to be evaluated through `Material::eval`: perfectly specular surfaces
+// (smooth mirrors and glass) can't reflect or refract a delta light towards the viewer.
+fn sample_delta_lights(r: &Ray, rec: &HitRecord, scene: &SceneView, media: &MediumStack) -> Color {
+    let mut direct: Color = Color::default();
+    for light in scene.delta_lights {
+        let sample: LightSample = light.sample(&rec.p);
+        if sample.radiance.near_zero() {
+            continue;
+        }
+        let f: Color = rec.material.eval(r, rec, &sample.direction);
+        if f.near_zero() {
+            continue;
+        }
+        let shadow_ray: Ray = Ray::new(&rec.p, &sample.direction, r.time);
+        if scene.world.hit(&shadow_ray, 0.001, sample.distance * (1.0 - 1e-6)).is_none() {
+            // Directional lights are infinitely far, the path can't stay inside a medium up to them
+            let transmittance: Color = if sample.distance.is_finite() { media.transmittance(sample.distance) } else { Color::new(1.0, 1.0, 1.0) };
+            direct = direct + f * transmittance * sample.radiance;
+        }
+    }
+    direct
+}
+
+// Carries the ray differentials through a specular bounce (pbrt's SpecularReflect and
+// SpecularTransmit): the offset rays leave from the offset points, turned by the change
+// of the incoming direction and of the normal across the footprint
+fn specular_differential(r: &Ray, rec: &HitRecord, scattered: &Ray) -> Ray {
+    let differential: RayDifferential = match r.differential {
+        Some(differential) => differential,
+        None => return *scattered,
+    };
+    let n: Vec3 = rec.normal;
+    let wo: Vec3 = -r.direction.normalize();
+    let wi: Vec3 = scattered.direction.normalize();
+    let tangential = |a: &Vec3| *a - n * a.dot(&n);
+    // eta_i / eta_t, from the ratio of the sines on both sides
+    let eta: f64 = if tangential(&wo).len() > 1e-8 { tangential(&wi).len() / tangential(&wo).len() } else { 1.0 };
+    let cos_o: f64 = wo.dot(&n);
+    let cos_i: f64 = wi.dot(&n).abs().max(1e-8);
+    let turn = |offset_direction: &Vec3, dndx: &Vec3| -> Vec3 {
+        let dwodx: Vec3 = -offset_direction.normalize() - wo;
+        let dcosdx: f64 = dwodx.dot(&n) + wo.dot(dndx);
+        if wi.dot(&n) > 0.0 {
+            wi - dwodx + (*dndx * cos_o + n * dcosdx) * 2.0
+        } else {
+            let mu: f64 = eta * cos_o - cos_i;
+            let dmudx: f64 = (eta - eta * eta * cos_o / cos_i) * dcosdx;
+            wi - dwodx * eta + *dndx * mu + n * dmudx
+        }
+    };
+    let footprint: Footprint = rec.footprint;
+    let dndx: Vec3 = rec.dndu * footprint.dudx + rec.dndv * footprint.dvdx;
+    let dndy: Vec3 = rec.dndu * footprint.dudy + rec.dndv * footprint.dvdy;
+    let turned: RayDifferential = RayDifferential { rx_origin: rec.p + footprint.dpdx,
+                                                    rx_direction: turn(&differential.rx_direction, &dndx),
+                                                    ry_origin: rec.p + footprint.dpdy,
+                                                    ry_direction: turn(&differential.ry_direction, &dndy) };
+    Ray { differential: Some(turned), ..*scattered }
+}
+
+// Scattering events of one random walk through a medium before the path is dropped
+const MAX_WALK_STEPS: i32 = 4096;
+
+// `bsdf_pdf` is the pdf the previous bounce sampled `r` with, None if it came
+// from the camera or a specular bounce (no light sampling was done for it).
+// `media` are the dielectrics the ray is travelling inside of.
+fn ray_color(r: &Ray, scene: &SceneView, depth: i32, bsdf_pdf: Option<f64>, media: &MediumStack, rng: &mut PRNG) -> Color {
     if depth <= 0 {
         return Color::default();
     }
-    match world.hit(r, 0.001, std::f64::INFINITY) {
-        Some(rec) => {
+    // Random walk: scattered isotropically inside the medium until the ray reaches a surface.
+    // The steps have their own budget, dense media take far more of them than bounces.
+    let mut ray: Ray = *r;
+    let mut bsdf_pdf: Option<f64> = bsdf_pdf;
+    let mut throughput: Color = Color::new(1.0, 1.0, 1.0);
+    let mut steps: i32 = 0;
+    let (hit, transmittance): (Option<HitRecord>, Color) = loop {
+        let hit: Option<HitRecord> = scene.world.hit(&ray, 0.001, f64::INFINITY);
+        let distance: f64 = match &hit {
+            Some(rec) => rec.t * ray.direction.len(),
+            None => f64::INFINITY,
+        };
+        let (collision, transmittance): (Option<f64>, Color) = media.sample_collision(distance, rng);
+        match collision {
+            Some(_) if steps >= MAX_WALK_STEPS => return Color::default(),
+            Some(t) => {
+                steps += 1;
+                throughput = throughput * transmittance;
+                ray = Ray::new(&ray.at(t / ray.direction.len()), &Vec3::random_unit_vector(rng), ray.time);
+                bsdf_pdf = None;
+            }
+            None => break (hit, throughput * transmittance),
+        }
+    };
+    let r: &Ray = &ray;
+    match hit {
+        Some(mut rec) => {
+            rec.compute_footprint(r);
+            let medium: Option<Medium> = rec.material.medium();
+            let interface: Material;
+            let rec: HitRecord = match medium {
+                Some(medium) if !media.is_interface(&medium) => {
+                    // Boundary of a lower priority medium: keep going straight through it
+                    let through: Ray = Ray { origin: rec.p, ..*r };
+                    let next_media: MediumStack = if rec.front_face { media.entered(&medium) } else { media.exited(&medium) };
+                    return transmittance * ray_color(&through, scene, depth - 1, None, &next_media, rng);
+                }
+                Some(medium) => {
+                    interface = rec.material.with_outside_index(media.exited(&medium).refraction_index());
+                    HitRecord { material: &interface, ..rec }
+                }
+                None => {
+                    rec
+                }
+            };
+
+            let mut emitted: Color = rec.material.emitted(r, &rec);
+            if let Some(pdf) = bsdf_pdf {
+                let light_pdf: f64 = light_sampling_pdf(&r.origin, &r.direction, &scene.lights, scene.background);
+                emitted = emitted * power_heuristic(pdf, light_pdf);
+            }
             match rec.material.scatter(r, &rec, rng) {
-                Some((scattered, attenuation)) => {
-                    attenuation * ray_color(&scattered, background, world, depth - 1, rng)
+                Some(srec) => {
+                    let next_media: MediumStack = match medium {
+                        Some(medium) if srec.scattered.direction.dot(&rec.normal) < 0.0 => {
+                            if rec.front_face { media.entered(&medium) } else { media.exited(&medium) }
+                        }
+                        _ => *media
+                    };
+                    let radiance: Color = match srec.pdf {
+                        Some(pdf) => {
+                            let direct: Color = sample_lights(r, &rec, scene, media, rng)
+                                + sample_delta_lights(r, &rec, scene, media);
+                            emitted + direct + srec.attenuation * ray_color(&srec.scattered, scene, depth - 1, Some(pdf), &next_media, rng)
+                        }
+                        None => {
+                            let scattered: Ray = specular_differential(r, &rec, &srec.scattered);
+                            emitted + srec.attenuation * ray_color(&scattered, scene, depth - 1, None, &next_media, rng)
+                        }
+                    };
+                    transmittance * radiance
                 }
                 _ => {
-                    rec.material.emitted(rec.u, rec.v, &rec.p)
+                    transmittance * emitted
                 }
             }
         }
         None => {
-            background
+            match bsdf_pdf {
+                Some(pdf) => scene.background.value(&r.direction) * power_heuristic(pdf, light_sampling_pdf(&r.origin, &r.direction, &scene.lights, scene.background)),
+                None => scene.background.value(&r.direction),
+            }
         }
     }
 }
 
 fn render_line(pixels: &mut [u8], camera: Camera, 
-               world: &Box<dyn Hittable>, 
-               background: Color,
-               image_width: usize, image_height: usize, 
+               scene: &SceneView,
+               image_height: usize, 
                samples_per_pixel: i32, 
                max_depth: i32, 
                y: usize) {
 
     let mut rng = Xoroshiro128Plus::from_entropy();
+    let image_width: usize = pixels.len() / 3;
 
     for x in 0..image_width {
         let mut pixel_color: Color = Color::default();
         for _ in 0..samples_per_pixel {
             let u: f64 = ((x as f64) + rng.gen::<f64>()) / ((image_width - 1) as f64);
             let v: f64 = (image_height as f64 - ((y as f64) + rng.gen::<f64>())) / ((image_height - 1) as f64);
-            let r: Ray = camera.get_ray(u, v, &mut rng);
-            pixel_color = pixel_color + ray_color(&r, background, &world, max_depth, &mut rng);
+            let r: Ray = camera.get_ray_differential(u, v, 1.0 / ((image_width - 1) as f64), 1.0 / ((image_height - 1) as f64), &mut rng);
+            pixel_color = pixel_color + ray_color(&r, scene, max_depth, None, &MediumStack::default(), &mut rng);
         }
         let scale: f64 = 1.0 / (samples_per_pixel as f64);
         let r: f64 = (scale * pixel_color.x).sqrt();
@@ -82,19 +316,22 @@ fn render_line(pixels: &mut [u8], camera: Camera,
 
 pub fn render(filename: &str, 
               camera: Camera, 
-              world: &Box<dyn Hittable>, 
-              background: Color,
+              scene: &Scene,
               image_width: usize, 
               image_height: usize, 
               samples_per_pixel: i32, 
               max_depth: i32) {
+    let view: SceneView = SceneView { world: scene.world.as_ref(),
+                                      lights: LightTree::new(&scene.lights),
+                                      delta_lights: &scene.delta_lights,
+                                      background: &scene.background };
     let mut pixels = vec![0; image_width * image_height * 3];
     let bands: Vec<(usize, &mut [u8])> = pixels.chunks_mut(image_width * 3).enumerate().collect();
 
     bands.into_par_iter().for_each(|(i, band)| {
-        render_line(band, camera, world, background, image_width, image_height, samples_per_pixel, max_depth, i);
+        render_line(band, camera, &view, image_height, samples_per_pixel, max_depth, i);
         eprintln!("Line {} Rendered!", i);
     });
     write_image(filename, &pixels, image_width, image_height).expect("error writing image: std::io::Error");
 
-}  
+}
\ No newline at end of file
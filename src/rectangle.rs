@@ -1,7 +1,16 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
+use crate::light_tree::LightBounds;
 use crate::material::Material;
+use crate::texture::Footprint;
 use crate::ray::Ray;
 use crate::vec3::{Vec3, Point3};
+use crate::PRNG;
+
+// Thickness given to the rects' bounding boxes
+const PADDING: f64 = 1e-4;
 
 pub struct XYRect {
     pub x0: f64,
@@ -32,19 +41,19 @@ pub struct YZRect {
 
 impl XYRect {
     pub fn new(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, material: &Material) -> Self {
-        Self { x0: x0, x1: x1, y0: y0, y1: y1, k: k, material: *material }
+        Self { x0: x0, x1: x1, y0: y0, y1: y1, k: k, material: material.clone() }
     }
 }
 
 impl XZRect {
     pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, material: &Material) -> Self {
-        Self { x0: x0, x1: x1, z0: z0, z1: z1, k: k, material: *material }
+        Self { x0: x0, x1: x1, z0: z0, z1: z1, k: k, material: material.clone() }
     }
 }
 
 impl YZRect {
     pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, material: &Material) -> Self {
-        Self { y0: y0, y1: y1, z0: z0, z1: z1, k: k, material: *material }
+        Self { y0: y0, y1: y1, z0: z0, z1: z1, k: k, material: material.clone() }
     }
 }
 
@@ -70,11 +79,39 @@ impl Hittable for XYRect {
             u: u,
             v: v,
             p: p,
+            object_p: p,
             normal: if front_face { normal } else { -normal },
+            dpdu: Vec3::new(self.x1 - self.x0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, self.y1 - self.y0, 0.0),
+            dndu: Vec3::default(),
+            dndv: Vec3::default(),
+            footprint: Footprint::default(),
             front_face: front_face,
             material: &self.material,
          })
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, direction, 0.0), 0.001, f64::INFINITY) {
+            Some(rec) => {
+                let area: f64 = (self.x1 - self.x0) * (self.y1 - self.y0);
+                let distance_squared: f64 = rec.t * rec.t * direction.sqlen();
+                let cosine: f64 = (direction.dot(&rec.normal) / direction.len()).abs();
+                distance_squared / (cosine * area)
+            }
+            None => 0.0
+        }
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        let random_point: Point3 = Point3::new(rng.gen_range(self.x0..self.x1), rng.gen_range(self.y0..self.y1), self.k);
+        random_point - *origin
+    }
+
+    fn light_bounds(&self) -> Option<LightBounds> {
+        Some(LightBounds { bounds: Aabb::new(&Point3::new(self.x0, self.y0, self.k - PADDING), &Point3::new(self.x1, self.y1, self.k + PADDING)),
+                           power: self.material.emitted_power((self.x1 - self.x0) * (self.y1 - self.y0)) })
+    }
 }
 
 impl Hittable for XZRect {
@@ -99,11 +136,39 @@ impl Hittable for XZRect {
             u: u,
             v: v,
             p: p,
+            object_p: p,
             normal: if front_face { normal } else { -normal },
+            dpdu: Vec3::new(self.x1 - self.x0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, self.z1 - self.z0),
+            dndu: Vec3::default(),
+            dndv: Vec3::default(),
+            footprint: Footprint::default(),
             front_face: front_face,
             material: &self.material,
          })
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, direction, 0.0), 0.001, f64::INFINITY) {
+            Some(rec) => {
+                let area: f64 = (self.x1 - self.x0) * (self.z1 - self.z0);
+                let distance_squared: f64 = rec.t * rec.t * direction.sqlen();
+                let cosine: f64 = (direction.dot(&rec.normal) / direction.len()).abs();
+                distance_squared / (cosine * area)
+            }
+            None => 0.0
+        }
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        let random_point: Point3 = Point3::new(rng.gen_range(self.x0..self.x1), self.k, rng.gen_range(self.z0..self.z1));
+        random_point - *origin
+    }
+
+    fn light_bounds(&self) -> Option<LightBounds> {
+        Some(LightBounds { bounds: Aabb::new(&Point3::new(self.x0, self.k - PADDING, self.z0), &Point3::new(self.x1, self.k + PADDING, self.z1)),
+                           power: self.material.emitted_power((self.x1 - self.x0) * (self.z1 - self.z0)) })
+    }
 }
 
 impl Hittable for YZRect {
@@ -128,9 +193,37 @@ impl Hittable for YZRect {
             u: u,
             v: v,
             p: p,
+            object_p: p,
             normal: if front_face { normal } else { -normal },
+            dpdu: Vec3::new(0.0, self.y1 - self.y0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, self.z1 - self.z0),
+            dndu: Vec3::default(),
+            dndv: Vec3::default(),
+            footprint: Footprint::default(),
             front_face: front_face,
             material: &self.material,
          })
     }
-}
\ No newline at end of file
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, direction, 0.0), 0.001, f64::INFINITY) {
+            Some(rec) => {
+                let area: f64 = (self.y1 - self.y0) * (self.z1 - self.z0);
+                let distance_squared: f64 = rec.t * rec.t * direction.sqlen();
+                let cosine: f64 = (direction.dot(&rec.normal) / direction.len()).abs();
+                distance_squared / (cosine * area)
+            }
+            None => 0.0
+        }
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        let random_point: Point3 = Point3::new(self.k, rng.gen_range(self.y0..self.y1), rng.gen_range(self.z0..self.z1));
+        random_point - *origin
+    }
+
+    fn light_bounds(&self) -> Option<LightBounds> {
+        Some(LightBounds { bounds: Aabb::new(&Point3::new(self.k - PADDING, self.y0, self.z0), &Point3::new(self.k + PADDING, self.y1, self.z1)),
+                           power: self.material.emitted_power((self.y1 - self.y0) * (self.z1 - self.z0)) })
+    }
+}
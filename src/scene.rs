@@ -8,10 +8,17 @@ use crate::hittable_list::HittableList;
 use crate::sphere::Sphere;
 use crate::material::Material;
 use crate::moving_sphere::MovingSphere;
-use crate::texture::Texture;
+use crate::texture::{Texture, TextureSpace};
+use crate::image_texture::WrapMode;
+use crate::cellular::CellularFeature;
 use crate::camera::Camera;
+use crate::background::Background;
+use crate::delta_light::DeltaLight;
+use crate::ies::IesProfile;
 use crate::rectangle::*;
 use crate::hbox::*;
+use crate::principled::Principled;
+use crate::spectrum;
 
 
 // Random scene from the end of book 1 (+ bounce and checkered ground)
@@ -99,6 +106,20 @@ pub fn two_spheres_cam(aspect_ratio: f64) -> Camera {
     Camera::new(&lookfrom, &lookat, &vup, 20.0, aspect_ratio, aperture, dist_to_focus, 0.0, 1.0)
 }
 
+// ---
+// Perlin noise ground and marble sphere from the second book
+pub fn two_perlin_spheres() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let noise: Material = Material::new_textured(&Texture::new_noise(0, 4.0));
+    let marble: Material = Material::new_textured(&Texture::new_marble(1, 4.0));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &noise)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 2.0, 0.0), 2.0, &marble)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
 // ---
 // Test scene for light
 pub fn light_test1() -> HittableList {
@@ -117,6 +138,11 @@ pub fn light_test1() -> HittableList {
     return world;
 }
 
+pub fn light_test1_lights() -> HittableList {
+    let light: Material = Material::new_light(&Color::new(1.0, 1.0, 1.0));
+    HittableList::new(vec![Box::new(Sphere::new(&Point3::new(0.0, 10.0, 0.0), 7.0, &light))])
+}
+
 pub fn light_test2() -> HittableList {
     let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
     let checker: Material = Material::new_textured(&Texture::new_checker(&Color::new(0.2, 0.3, 0.1), &Color::new(0.9, 0.9, 0.9)));
@@ -130,6 +156,11 @@ pub fn light_test2() -> HittableList {
     return world;
 }
 
+pub fn light_test2_lights() -> HittableList {
+    let light: Material = Material::new_light(&Color::new(1.0, 1.0, 1.0));
+    HittableList::new(vec![Box::new(XYRect::new(3.0, 5.0, 1.0, 3.0, -2.0, &light))])
+}
+
 pub fn light_test2_cam(aspect_ratio: f64) -> Camera {
     let lookfrom: Point3 = Point3::new(26.0, 3.0, 6.0);
     let lookat: Point3 = Point3::new(0.0, 2.0, 0.0);
@@ -155,7 +186,7 @@ pub fn cornell() -> HittableList {
     object_list.push(Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, &white)));
     object_list.push(Box::new(XYRect::new(0.0, 555.0, 0.0, 555.0, 555.0, &white)));
 
-    let box1 = Box::new(Translate::new(Box::new(RotateY::new(Box::new(HBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(165.0, 165.0, 165.0), white)), -18.0)), Vec3::new(130.0, 0.0, 65.0)));
+    let box1 = Box::new(Translate::new(Box::new(RotateY::new(Box::new(HBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(165.0, 165.0, 165.0), white.clone())), -18.0)), Vec3::new(130.0, 0.0, 65.0)));
     let box2 = Box::new(Translate::new(Box::new(RotateY::new(Box::new(HBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(165.0, 330.0, 165.0), white)), 15.0)), Vec3::new(265.0, 0.0, 295.0)));
     object_list.push(box1);
     object_list.push(box2);
@@ -164,6 +195,12 @@ pub fn cornell() -> HittableList {
     return world;
 }
 
+// Sampled for direct lighting, must match the light in `cornell`
+pub fn cornell_lights() -> HittableList {
+    let light: Material = Material::new_light(&Color::new(15.0, 15.0, 15.0));
+    HittableList::new(vec![Box::new(XZRect::new(213.0, 343.0, 227.0, 332.0, 554.0, &light))])
+}
+
 pub fn cornell_cam(aspect_ratio: f64) -> Camera {
     let lookfrom: Point3 = Point3::new(278.0, 278.0, -800.0);
     let lookat: Point3 = Point3::new(278.0, 278.0, 0.0);
@@ -172,4 +209,532 @@ pub fn cornell_cam(aspect_ratio: f64) -> Camera {
     let aperture: f64 = 0.0;
 
     Camera::new(&lookfrom, &lookat, &vup, 40.0, aspect_ratio, aperture, dist_to_focus, 0.0, 1.0)
-}
\ No newline at end of file
+}
+
+// ---
+// Rough gold, copper and aluminium spheres under a small light
+pub fn conductors() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(20.0, 20.0, 20.0));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &Material::new_gold(0.2))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &Material::new_copper(0.35))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &Material::new_conductor(&Color::new(1.657, 0.880, 0.521), &Color::new(9.224, 6.270, 4.837), 0.5, 0.8))));
+    object_list.push(Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 6.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+pub fn conductors_lights() -> HittableList {
+    let light: Material = Material::new_light(&Color::new(20.0, 20.0, 20.0));
+    HittableList::new(vec![Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 6.0, &light))])
+}
+
+pub fn conductors_cam(aspect_ratio: f64) -> Camera {
+    let lookfrom: Point3 = Point3::new(13.0, 3.0, 0.0);
+    let lookat: Point3 = Point3::new(0.0, 1.0, 0.0);
+    let vup: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus: f64 = 10.0;
+    let aperture: f64 = 0.0;
+
+    Camera::new(&lookfrom, &lookat, &vup, 25.0, aspect_ratio, aperture, dist_to_focus, 0.0, 1.0)
+}
+
+// ---
+// Clear and frosted glass spheres in front of colored stripes
+pub fn frosted_glass() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let white: Material = Material::new_lambertian(&Color::new(0.73, 0.73, 0.73));
+    let red: Material = Material::new_lambertian(&Color::new(0.65, 0.05, 0.05));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &white)));
+    object_list.push(Box::new(YZRect::new(0.0, 10.0, -10.0, 10.0, -4.0, &white)));
+    for i in 0..10 {
+        let z: f64 = -5.0 + (i as f64);
+        object_list.push(Box::new(YZRect::new(0.0, 10.0, z, z + 0.5, -3.99, &red)));
+    }
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &Material::new_dielectric(1.5))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &Material::new_rough_dielectric(1.5, 0.2))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &Material::new_rough_dielectric(1.5, 0.5))));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+pub fn frosted_glass_lights() -> HittableList {
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    HittableList::new(vec![Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light))])
+}
+
+pub fn frosted_glass_cam(aspect_ratio: f64) -> Camera {
+    let lookfrom: Point3 = Point3::new(13.0, 3.0, 0.0);
+    let lookat: Point3 = Point3::new(0.0, 1.0, 0.0);
+    let vup: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus: f64 = 10.0;
+    let aperture: f64 = 0.0;
+
+    Camera::new(&lookfrom, &lookat, &vup, 25.0, aspect_ratio, aperture, dist_to_focus, 0.0, 1.0)
+}
+
+// ---
+// Tinted glass of increasing thickness, and a glass ball with a liquid core.
+// The liquid has the higher priority, so inside it the glass is ignored.
+pub fn colored_glass() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let white: Material = Material::new_lambertian(&Color::new(0.73, 0.73, 0.73));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let blue_glass: Material = Material::new_absorbing_dielectric(1.5, 0.0, &Color::new(0.3, 0.5, 0.9), 1.0, 1);
+    let glass: Material = Material::new_absorbing_dielectric(1.5, 0.0, &Color::new(1.0, 1.0, 1.0), 1.0, 1);
+    let wine: Material = Material::new_absorbing_dielectric(1.33, 0.0, &Color::new(0.8, 0.1, 0.15), 0.5, 2);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &white)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 0.5, -2.5), 0.5, &blue_glass)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &blue_glass)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &glass)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 0.9, 2.5), 0.85, &wine)));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Principled material swatches: plastic, metal, clearcoat paint, velvet and glass
+pub fn principled() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let orange: Texture = Texture::new_solid(&Color::new(0.8, 0.3, 0.05));
+
+    let plastic: Principled = Principled { roughness: Texture::new_scalar(0.3), ..Principled::new(&orange) };
+    let metal: Principled = Principled { metallic: Texture::new_scalar(1.0), roughness: Texture::new_scalar(0.4),
+                                         ..Principled::new(&Texture::new_solid(&Color::new(0.9, 0.9, 0.9))) };
+    let paint: Principled = Principled { clearcoat: Texture::new_scalar(1.0), roughness: Texture::new_scalar(0.6),
+                                         ..Principled::new(&Texture::new_solid(&Color::new(0.05, 0.1, 0.5))) };
+    let velvet: Principled = Principled { sheen: Texture::new_scalar(1.0), roughness: Texture::new_scalar(1.0), specular: Texture::new_scalar(0.0),
+                                          ..Principled::new(&Texture::new_solid(&Color::new(0.4, 0.02, 0.05))) };
+    let glass: Principled = Principled { transmission: Texture::new_scalar(1.0), roughness: Texture::new_scalar(0.1),
+                                         ..Principled::new(&Texture::new_solid(&Color::new(1.0, 1.0, 1.0))) };
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    for (i, swatch) in [plastic, metal, paint, velvet, glass].iter().enumerate() {
+        let z: f64 = -4.4 + 2.2 * (i as f64);
+        object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, z), 1.0, &Material::new_principled(swatch))));
+    }
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Layered materials: a checker blended into lambertian, varnished diffuse and coated copper
+pub fn layered() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let red: Material = Material::new_lambertian(&Color::new(0.65, 0.05, 0.05));
+    let gold: Material = Material::new_gold(0.3);
+    let mask: Texture = Texture::new_checker(&Color::new(1.0, 1.0, 1.0), &Color::new(0.0, 0.0, 0.0));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &Material::new_mix(&red, &gold, &mask))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &Material::new_clearcoat(&red, 1.5, 0.0))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &Material::new_clearcoat(&Material::new_copper(0.5), 1.5, 0.1))));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Lambertian next to increasingly rough Oren-Nayar clay
+pub fn rough_diffuse() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_oren_nayar(&Color::new(0.5, 0.5, 0.5), 20.0);
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let clay: Color = Color::new(0.7, 0.4, 0.3);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &Material::new_lambertian(&clay))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &Material::new_oren_nayar(&clay, 20.0))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &Material::new_oren_nayar(&clay, 60.0))));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Subsurface scattering: a wax box, a marble ball and a ball of milk
+pub fn subsurface() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let wax: Material = Material::new_subsurface(&Color::new(0.99, 0.9, 0.6), &Color::new(0.3, 0.2, 0.1), 1.4, 0.3);
+    let marble: Material = Material::new_subsurface(&Color::new(0.999, 0.999, 0.995), &Color::new(0.2, 0.2, 0.2), 1.5, 0.0);
+    let milk: Material = Material::new_subsurface(&Color::new(0.9999, 0.9995, 0.998), &Color::new(0.05, 0.06, 0.08), 1.35, 0.0);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Translate::new(Box::new(RotateY::new(Box::new(HBox::new(Point3::new(-0.8, 0.0, -0.8), Point3::new(0.8, 1.6, 0.8), wax)), 30.0)), Vec3::new(0.0, 0.0, -2.5))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &marble)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &milk)));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Thin films: a soap bubble, oil on glossy glass and anodized titanium
+pub fn thin_film() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let bubble: Material = Material::new_thin_film(&Material::new_dielectric(1.0), &Texture::new_scalar(400.0), 1.33);
+    let oil: Material = Material::new_thin_film(&Material::new_rough_dielectric(1.5, 0.05), &Texture::new_scalar(500.0), 1.47);
+    let titanium: Material = Material::new_conductor(&Color::new(2.74, 2.54, 2.27), &Color::new(3.81, 3.43, 3.04), 0.2, 0.0);
+    let anodized: Material = Material::new_thin_film(&titanium, &Texture::new_scalar(250.0), 2.4);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &bubble)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &oil)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &anodized)));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Fabrics: red velvet, sheen over a blue Oren-Nayar cloth and over plain lambertian
+pub fn fabric() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let velvet: Material = Material::new_velvet(&Texture::new_solid(&Color::new(0.8, 0.05, 0.1)), 0.5);
+    let cloth: Material = Material::new_sheen(&Material::new_oren_nayar(&Color::new(0.05, 0.1, 0.4), 30.0), &Texture::new_solid(&Color::new(0.6, 0.7, 1.0)), 0.4);
+    let satin: Material = Material::new_sheen(&Material::new_lambertian(&Color::new(0.4, 0.35, 0.1)), &Texture::new_solid(&Color::new(1.0, 1.0, 1.0)), 0.3);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &velvet)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &cloth)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &satin)));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Emitters: a warm one-sided ceiling panel given by its power, a textured screen
+// on the back wall (dark from behind) and a daylight colored glowing ball
+pub fn emitters() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let white: Material = Material::new_lambertian(&Color::new(0.73, 0.73, 0.73));
+    let panel: Material = Material::new_light_power(&spectrum::blackbody(2700.0), 250.0, 4.0, false);
+    let screen: Material = Material::new_textured_light(&Texture::new_checker(&Color::new(0.1, 0.2, 1.0), &Color::new(1.0, 0.9, 0.2)), 2.0, false);
+    let ball: Material = Material::new_blackbody_light(6500.0, 4.0, true);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &white)));
+    object_list.push(Box::new(YZRect::new(0.0, 10.0, -10.0, 10.0, -4.0, &white)));
+    object_list.push(Box::new(YZRect::new(1.0, 4.0, -3.0, 3.0, -3.99, &screen)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &white)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 0.5, 0.0), 0.5, &ball)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &white)));
+    object_list.push(Box::new(FlipFace::new(Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 6.0, &panel)))));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+pub fn emitters_lights() -> HittableList {
+    let panel: Material = Material::new_light_power(&spectrum::blackbody(2700.0), 250.0, 4.0, false);
+    HittableList::new(vec![Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 6.0, &panel))])
+}
+
+// ---
+// Normal mapping: facets tilted alternately left and right
+pub fn normal_mapping() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let facets: Texture = Texture::new_checker(&Color::new(0.8, 0.5, 1.0), &Color::new(0.2, 0.5, 1.0));
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let plastic: Material = Material::new_normal_mapped(&Material::new_lambertian(&Color::new(0.8, 0.3, 0.1)), &facets, 1.0);
+    let metal: Material = Material::new_normal_mapped(&Material::new_gold(0.2), &facets, 1.0);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -1.5), 1.0, &plastic)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 1.5), 1.0, &metal)));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Opacity masks: a checkered fence in front of the spheres, and a half opaque ball
+pub fn cutouts() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let mask: Texture = Texture::new_checker(&Color::new(1.0, 1.0, 1.0), &Color::new(0.0, 0.0, 0.0));
+    let fence: Material = Material::new_cutout(&Material::new_lambertian(&Color::new(0.2, 0.5, 0.2)), &mask);
+    let ghost: Material = Material::new_cutout(&Material::new_lambertian(&Color::new(0.1, 0.2, 0.8)), &Texture::new_scalar(0.5));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -1.5), 1.0, &Material::new_gold(0.2))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(-1.0, 1.0, 1.5), 1.0, &ghost)));
+    object_list.push(Box::new(YZRect::new(0.0, 1.5, -4.0, 4.0, 2.0, &fence)));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Globe from the second book, textured with an image such as earthmap.jpg
+pub fn earth(path: &str) -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let texture: Texture = Texture::new_image(path, WrapMode::Repeat).expect("error reading texture: image::ImageError");
+    let surface: Material = Material::new_textured(&texture);
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 0.0, 0.0), 2.0, &surface)));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+pub fn earth_cam(aspect_ratio: f64) -> Camera {
+    let lookfrom: Point3 = Point3::new(13.0, 2.0, 3.0);
+    let lookat: Point3 = Point3::new(0.0, 0.0, 0.0);
+    let vup: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus: f64 = 10.0;
+    let aperture: f64 = 0.0;
+
+    Camera::new(&lookfrom, &lookat, &vup, 20.0, aspect_ratio, aperture, dist_to_focus, 0.0, 1.0)
+}
+
+// ---
+// Texture graph: a tinted marble, and two colors mixed by sharpened noise
+pub fn texture_graph() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let tinted: Texture = Texture::new_multiply(&Texture::new_marble(1, 4.0), &Texture::new_solid(&Color::new(0.9, 0.6, 0.3)));
+    let mask: Texture = Texture::new_remap(&Texture::new_noise(2, 3.0), 0.4, 0.6, 0.0, 1.0);
+    let blotches: Texture = Texture::new_mix(&Texture::new_solid(&Color::new(0.8, 0.1, 0.1)), &Texture::new_solid(&Color::new(0.1, 0.2, 0.8)), &mask);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -1.5), 1.0, &Material::new_textured(&tinted))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 1.5), 1.0, &Material::new_textured(&blotches))));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Cornell box with checkers at the box's scale: squares on the floor in uv,
+// and cubes that stay attached to the rotated boxes
+pub fn checkered_cornell() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let red: Material = Material::new_lambertian(&Color::new(0.65, 0.05, 0.05));
+    let white: Material = Material::new_lambertian(&Color::new(0.73, 0.73, 0.73));
+    let green: Material = Material::new_lambertian(&Color::new(0.12, 0.45, 0.15));
+    let light: Material = Material::new_light(&Color::new(15.0, 15.0, 15.0));
+    let floor: Material = Material::new_textured(&Texture::new_uv_checker(&Color::new(0.73, 0.73, 0.73), &Color::new(0.1, 0.1, 0.1), 8.0, 8.0));
+    let cubes: Texture = Texture::new_checker_3d(&Color::new(0.8, 0.7, 0.2), &Color::new(0.2, 0.3, 0.7), 33.0);
+    let boxes: Material = Material::new_textured(&Texture::new_in_space(&cubes, TextureSpace::Object));
+
+    object_list.push(Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, &green)));
+    object_list.push(Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, &red)));
+    object_list.push(Box::new(XZRect::new(213.0, 343.0, 227.0, 332.0, 554.0, &light)));
+    object_list.push(Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, &floor)));
+    object_list.push(Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, &white)));
+    object_list.push(Box::new(XYRect::new(0.0, 555.0, 0.0, 555.0, 555.0, &white)));
+
+    let box1 = Box::new(Translate::new(Box::new(RotateY::new(Box::new(HBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(165.0, 165.0, 165.0), boxes.clone())), -18.0)), Vec3::new(130.0, 0.0, 65.0)));
+    let box2 = Box::new(Translate::new(Box::new(RotateY::new(Box::new(HBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(165.0, 330.0, 165.0), boxes)), 15.0)), Vec3::new(265.0, 0.0, 295.0)));
+    object_list.push(box1);
+    object_list.push(box2);
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Procedural patterns: brick back wall, tiled floor, a stone ball with
+// cellular cracks and a wooden ball
+pub fn procedural() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    let bricks: Texture = Texture::new_brick(&Color::new(0.55, 0.2, 0.1), &Color::new(0.7, 0.7, 0.65), 0.5, 0.25, 0.04);
+    // The wall's u runs up along y: turn the rows horizontal and scale to scene units
+    let wall: Texture = Texture::new_uv_transform(&bricks, 20.0, 10.0, 0.0, 0.0, 90.0);
+    let tiles: Texture = Texture::new_tiles(&Color::new(0.8, 0.8, 0.75), &Color::new(0.2, 0.2, 0.2), 0.5, 0.02);
+    let edges: Texture = Texture::new_cellular(3, 3.0, CellularFeature::Edge, &Color::default(), &Color::new(1.0, 1.0, 1.0));
+    let cracks: Texture = Texture::new_mix(&Texture::new_scalar(0.05), &Texture::new_solid(&Color::new(0.6, 0.6, 0.55)), &Texture::new_remap(&edges, 0.0, 0.05, 0.0, 1.0));
+    let wood: Texture = Texture::new_wood(4, 4.0, 0.3, &Color::new(0.75, 0.5, 0.3), &Color::new(0.4, 0.2, 0.1));
+    let roughness: Texture = Texture::new_remap(&Texture::new_cellular(5, 3.0, CellularFeature::Cell, &Color::default(), &Color::new(1.0, 1.0, 1.0)), 0.0, 1.0, 0.3, 0.7);
+    let mut stone: Principled = Principled::new(&cracks);
+    stone.roughness = roughness;
+
+    object_list.push(Box::new(YZRect::new(0.0, 10.0, -10.0, 10.0, -4.0, &Material::new_textured(&wall))));
+    object_list.push(Box::new(XZRect::new(-10.0, 10.0, -10.0, 10.0, 0.0, &Material::new_textured(&Texture::new_in_space(&tiles, TextureSpace::World)))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -1.5), 1.0, &Material::new_principled(&stone))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 1.5), 1.0, &Material::new_textured(&wood))));
+    object_list.push(Box::new(XYRect::new(-3.0, 3.0, 1.0, 5.0, 8.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+// ---
+// Checkered plane running to the horizon, directly and in a mirror ball,
+// to check texture filtering against aliasing
+pub fn texture_filtering() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let checker: Texture = Texture::new_uv_checker(&Color::new(0.9, 0.9, 0.9), &Color::new(0.1, 0.1, 0.1), 400.0, 400.0);
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+
+    object_list.push(Box::new(XZRect::new(-200.0, 200.0, -200.0, 200.0, 0.0, &Material::new_textured(&checker))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &Material::new_metal(&Color::new(0.9, 0.9, 0.9), 0.0))));
+    object_list.push(Box::new(XZRect::new(-5.0, 5.0, -5.0, 5.0, 20.0, &light)));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+pub fn texture_filtering_lights() -> HittableList {
+    let light: Material = Material::new_light(&Color::new(4.0, 4.0, 4.0));
+    HittableList::new(vec![Box::new(XZRect::new(-5.0, 5.0, -5.0, 5.0, 20.0, &light))])
+}
+
+pub fn texture_filtering_cam(aspect_ratio: f64) -> Camera {
+    let lookfrom: Point3 = Point3::new(6.0, 1.5, 0.0);
+    let lookat: Point3 = Point3::new(0.0, 1.0, 0.0);
+    let vup: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus: f64 = 10.0;
+    let aperture: f64 = 0.0;
+
+    Camera::new(&lookfrom, &lookat, &vup, 40.0, aspect_ratio, aperture, dist_to_focus, 0.0, 1.0)
+}
+
+// ---
+// Objects lit only by an HDR environment map (`.hdr` or `.exr`), no lights list
+pub fn environment() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let ground: Material = Material::new_lambertian(&Color::new(0.5, 0.5, 0.5));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &Material::new_lambertian(&Color::new(0.8, 0.3, 0.2)))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &Material::new_dielectric(1.5))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &Material::new_metal(&Color::new(0.9, 0.9, 0.9), 0.05))));
+
+    let world = HittableList::new(object_list);
+    return world;
+}
+
+pub fn environment_background(path: &str, rotation: f64, intensity: f64) -> Background {
+    Background::new_environment(path, rotation, intensity).expect("error reading environment map: image::ImageError")
+}
+
+// ---
+// Lit only by delta lights: a warm spot on the diffuse front ball, a blue point light
+// on the left and a dim sun from the back. The middle ball is fuzzy metal, a perfect
+// mirror wouldn't show any of them.
+pub fn spotlights() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let white: Material = Material::new_lambertian(&Color::new(0.73, 0.73, 0.73));
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &white)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -2.5), 1.0, &white)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &Material::new_metal(&Color::new(0.9, 0.8, 0.6), 0.2))));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 2.5), 1.0, &white)));
+
+    HittableList::new(object_list)
+}
+
+pub fn spotlights_delta_lights() -> Vec<DeltaLight> {
+    vec![DeltaLight::new_spot(&Point3::new(4.0, 6.0, 2.5), &Point3::new(0.0, 0.5, 2.5), &(spectrum::blackbody(3200.0) * 60.0), 20.0, 12.0),
+         DeltaLight::new_point(&Point3::new(2.0, 1.5, -4.5), &Color::new(1.0, 2.0, 8.0)),
+         DeltaLight::new_directional(&Vec3::new(1.0, -1.0, 0.5), &Color::new(0.2, 0.2, 0.2))]
+}
+
+// ---
+// Hundreds of small lights of very different power over a plane, for the light tree
+fn many_lights_spheres() -> Vec<Sphere> {
+    let mut rng: Xoroshiro128Plus = Xoroshiro128Plus::seed_from_u64(7);
+    (0..300).map(|_| {
+        let center: Point3 = Point3::new(rng.gen_range(-10.0..10.0), rng.gen_range(0.2..1.5), rng.gen_range(-10.0..10.0));
+        let intensity: f64 = 10.0_f64.powf(rng.gen_range(-0.5..2.0));
+        let light: Material = Material::new_blackbody_light(rng.gen_range(1800.0..9000.0), intensity, true);
+        Sphere::new(&center, 0.05, &light)
+    }).collect()
+}
+
+pub fn many_lights() -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let white: Material = Material::new_lambertian(&Color::new(0.73, 0.73, 0.73));
+
+    object_list.push(Box::new(XZRect::new(-12.0, 12.0, -12.0, 12.0, 0.0, &white)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &white)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(-3.0, 1.0, 3.0), 1.0, &Material::new_metal(&Color::new(0.9, 0.9, 0.9), 0.1))));
+    for light in many_lights_spheres() {
+        object_list.push(Box::new(light));
+    }
+
+    HittableList::new(object_list)
+}
+
+pub fn many_lights_lights() -> HittableList {
+    HittableList::new(many_lights_spheres().into_iter().map(|light| Box::new(light) as Box<dyn Hittable>).collect())
+}
+
+pub fn many_lights_cam(aspect_ratio: f64) -> Camera {
+    let lookfrom: Point3 = Point3::new(14.0, 8.0, 14.0);
+    let lookat: Point3 = Point3::new(0.0, 0.0, 0.0);
+    let vup: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus: f64 = 10.0;
+    let aperture: f64 = 0.0;
+
+    Camera::new(&lookfrom, &lookat, &vup, 40.0, aspect_ratio, aperture, dist_to_focus, 0.0, 1.0)
+}
+
+// ---
+// Luminaires from an IES file washing a wall: three point lights aimed down
+// along the wall and a ceiling panel with the same profile
+pub fn photometric(path: &str) -> HittableList {
+    let mut object_list: Vec<Box<dyn Hittable>> = Vec::new();
+    let profile: IesProfile = IesProfile::load(path).expect("error reading IES profile: ies::IesError");
+    let white: Material = Material::new_lambertian(&Color::new(0.73, 0.73, 0.73));
+    let panel: Material = Material::new_photometric_light(&spectrum::blackbody(4000.0), 4.0, false, &profile);
+
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &white)));
+    object_list.push(Box::new(YZRect::new(0.0, 10.0, -10.0, 10.0, -4.0, &white)));
+    object_list.push(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &white)));
+    object_list.push(Box::new(FlipFace::new(Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 6.0, &panel)))));
+
+    HittableList::new(object_list)
+}
+
+pub fn photometric_lights(path: &str) -> HittableList {
+    let profile: IesProfile = IesProfile::load(path).expect("error reading IES profile: ies::IesError");
+    let panel: Material = Material::new_photometric_light(&spectrum::blackbody(4000.0), 4.0, false, &profile);
+    HittableList::new(vec![Box::new(FlipFace::new(Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 6.0, &panel))))])
+}
+
+pub fn photometric_delta_lights(path: &str) -> Vec<DeltaLight> {
+    let profile: IesProfile = IesProfile::load(path).expect("error reading IES profile: ies::IesError");
+    [-2.5, 0.0, 2.5].iter().map(|z| {
+        DeltaLight::new_photometric(&Point3::new(-3.5, 4.0, *z), &Point3::new(-3.5, 0.0, *z), 0.0, &(spectrum::blackbody(3000.0) * 20.0), &profile)
+    }).collect()
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use rand::prelude::Rng;
+
+use crate::vec3::{Vec3, Color};
+use crate::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+use crate::material::{self, Material, ScatterRecord};
+use crate::onb::Onb;
+use crate::PRNG;
+
+// Cosines at which the directional albedo of the sheen lobe is tabulated
+const ALBEDO_STEPS: usize = 32;
+
+// Untinted sheen bsdf times cosine, in the local shading frame
+fn charlie(wo: &Vec3, wi: &Vec3, alpha: f64) -> f64 {
+    if wo.z <= 0.0 || wi.z <= 0.0 {
+        return 0.0;
+    }
+    let h: Vec3 = (*wo + *wi).normalize();
+    let sin_h: f64 = (1.0 - h.z * h.z).max(0.0).sqrt();
+    let distribution: f64 = (2.0 + 1.0 / alpha) * sin_h.powf(1.0 / alpha) / (2.0 * std::f64::consts::PI);
+    let visibility: f64 = 1.0 / (4.0 * (wi.z + wo.z - wi.z * wo.z));
+    distribution * visibility * wi.z
+}
+
+// Fraction of the light reaching the surface along `wo` that the lobe reflects,
+// integrated with the midpoint rule over (cos theta, phi)
+fn charlie_albedo(wo: &Vec3, alpha: f64) -> f64 {
+    const THETA_STEPS: usize = 32;
+    const PHI_STEPS: usize = 64;
+    let mut albedo: f64 = 0.0;
+    for i in 0..THETA_STEPS {
+        let cos_theta: f64 = (i as f64 + 0.5) / THETA_STEPS as f64;
+        let sin_theta: f64 = (1.0 - cos_theta * cos_theta).sqrt();
+        for j in 0..PHI_STEPS {
+            let phi: f64 = 2.0 * std::f64::consts::PI * (j as f64 + 0.5) / PHI_STEPS as f64;
+            albedo += charlie(wo, &Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta), alpha);
+        }
+    }
+    albedo * 2.0 * std::f64::consts::PI / (THETA_STEPS * PHI_STEPS) as f64
+}
+
+// Retro-reflective fabric sheen, the "Charlie" distribution of Estevez and Kulla 2017
+// with Ashikhmin's visibility term. Without a base it is a velvet on its own, with one
+// it is layered over the base, which only gets the energy the sheen doesn't reflect.
+#[derive(Debug, Clone)]
+pub struct Sheen {
+    pub base: Option<Arc<Material>>,
+    pub tint: Texture,
+    pub alpha: f64,
+    // Directional albedo of the untinted lobe for cos theta_o in [0, 1]
+    albedo: Vec<f64>,
+}
+
+impl Sheen {
+    pub fn new(base: Option<&Material>, tint: &Texture, roughness: f64) -> Self {
+        let alpha: f64 = (roughness * roughness).clamp(0.07, 1.0);
+        let albedo: Vec<f64> = (0..ALBEDO_STEPS).map(|i| {
+            let cos_theta: f64 = (i as f64 / (ALBEDO_STEPS - 1) as f64).max(1e-3);
+            charlie_albedo(&Vec3::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, cos_theta), alpha)
+        }).collect();
+        Self { base: base.map(|base| Arc::new(base.clone())),
+               tint: tint.clone(),
+               alpha,
+               albedo }
+    }
+
+    // Share of the base left under the sheen when seen from `r`: 1 - tint * albedo(cos theta_o),
+    // with the brightest channel of the tint so no channel gains energy
+    fn base_weight(&self, r: &Ray, rec: &HitRecord) -> f64 {
+        let (_, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        let x: f64 = wo.z.clamp(0.0, 1.0) * (ALBEDO_STEPS - 1) as f64;
+        let i: usize = (x as usize).min(ALBEDO_STEPS - 2);
+        let albedo: f64 = self.albedo[i] + (self.albedo[i + 1] - self.albedo[i]) * (x - i as f64);
+        let tint: Color = self.tint.sample(rec);
+        (1.0 - tint.x.max(tint.y).max(tint.z) * albedo).clamp(0.0, 1.0)
+    }
+
+    // Probability of sampling the sheen lobe rather than the base
+    fn sheen_probability(&self) -> f64 {
+        if self.base.is_some() { 0.5 } else { 1.0 }
+    }
+
+    fn sheen_eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let (uvw, wo): (Onb, Vec3) = material::local_frame(r, rec);
+        let wi: Vec3 = uvw.to_local(&direction.normalize());
+        self.tint.sample(rec) * charlie(&wo, &wi, self.alpha)
+    }
+
+    fn sheen_pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let (uvw, _): (Onb, Vec3) = material::local_frame(r, rec);
+        let wi: Vec3 = uvw.to_local(&direction.normalize());
+        if wi.z <= 0.0 { 0.0 } else { wi.z / std::f64::consts::PI }
+    }
+
+    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<ScatterRecord> {
+        let sheen_probability: f64 = self.sheen_probability();
+        let direction: Vec3 = match &self.base {
+            Some(base) if rng.gen::<f64>() >= sheen_probability => {
+                let srec: ScatterRecord = base.scatter(r, rec, rng)?;
+                if srec.pdf.is_none() {
+                    return Some(ScatterRecord { attenuation: srec.attenuation * (self.base_weight(r, rec) / (1.0 - sheen_probability)), ..srec });
+                }
+                srec.scattered.direction
+            }
+            _ => {
+                let (uvw, _): (Onb, Vec3) = material::local_frame(r, rec);
+                uvw.local(&Vec3::random_cosine_direction(rng))
+            }
+        };
+        let pdf: f64 = self.pdf(r, rec, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord { scattered: Ray::new(&rec.p, &direction, r.time),
+                             attenuation: self.eval(r, rec, &direction) / pdf,
+                             pdf: Some(pdf) })
+    }
+
+    pub fn eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let sheen: Color = self.sheen_eval(r, rec, direction);
+        match &self.base {
+            Some(base) => sheen + base.eval(r, rec, direction) * self.base_weight(r, rec),
+            None => sheen,
+        }
+    }
+
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let sheen_probability: f64 = self.sheen_probability();
+        let sheen: f64 = sheen_probability * self.sheen_pdf(r, rec, direction);
+        match &self.base {
+            Some(base) => sheen + (1.0 - sheen_probability) * base.pdf(r, rec, direction),
+            None => sheen,
+        }
+    }
+
+    pub fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        match &self.base {
+            Some(base) => base.emitted(r, rec),
+            None => Color::default(),
+        }
+    }
+}
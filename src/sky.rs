@@ -0,0 +1,164 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::background::{EnvironmentMap, uv_to_direction, luminance};
+use crate::image_texture::ImageTexture;
+use crate::onb::Onb;
+use crate::spectrum;
+use crate::vec3::{Vec3, Color};
+use crate::PRNG;
+
+// Preetham luminances are in kcd/m^2, this brings a clear zenith to about 1
+const LUMINANCE_SCALE: f64 = 0.1;
+// Luminance of the sun outside the atmosphere, kcd/m^2
+const SUN_LUMINANCE: f64 = 1.6e6;
+const SUN_TEMPERATURE: f64 = 5778.0;
+// The sun is about half a degree wide
+const SUN_ANGULAR_RADIUS: f64 = 0.00465;
+// Resolution of the table the sky is importance sampled with
+const TABLE_WIDTH: usize = 128;
+const TABLE_HEIGHT: usize = 64;
+
+// Perez et al. all weather luminance distribution, A..E coefficients
+fn perez(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e]: [f64; 5] = *coefficients;
+    (1.0 + a * (b / cos_theta.max(1e-3)).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+}
+
+// Relative optical mass of the atmosphere looking `theta` away from the zenith (Kasten & Young)
+fn optical_mass(theta: f64) -> f64 {
+    1.0 / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253))
+}
+
+// Preetham, Shirley & Smits "A practical analytic model for daylight" (1999): the clear sky
+// from the sun position and the turbidity (2 is very clear, 10 hazy). Below the horizon is black.
+#[derive(Debug, Clone)]
+struct Preetham {
+    sun_direction: Vec3,
+    // Perez coefficients for Y, x and y
+    coefficients: [[f64; 5]; 3],
+    // Y, x, y at the zenith divided by the Perez function there
+    zenith: [f64; 3],
+}
+
+impl Preetham {
+    fn new(sun_direction: &Vec3, turbidity: f64) -> Self {
+        let t: f64 = turbidity;
+        // The fit only holds for a sun above the horizon
+        let theta_s: f64 = sun_direction.y.clamp(0.0, 1.0).acos();
+        let coefficients: [[f64; 5]; 3] = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+        let chi: f64 = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y: f64 = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let (t2, s, s2, s3): (f64, f64, f64, f64) = (t * t, theta_s, theta_s * theta_s, theta_s.powi(3));
+        let zenith_x: f64 = t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let zenith_y_chroma: f64 = t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+        let zenith: [f64; 3] = [zenith_y / perez(&coefficients[0], 1.0, theta_s),
+                                zenith_x / perez(&coefficients[1], 1.0, theta_s),
+                                zenith_y_chroma / perez(&coefficients[2], 1.0, theta_s)];
+        Self { sun_direction: *sun_direction, coefficients, zenith }
+    }
+
+    fn value(&self, direction: &Vec3) -> Color {
+        let d: Vec3 = direction.normalize();
+        if d.y <= 0.0 {
+            return Color::default();
+        }
+        let gamma: f64 = d.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+        let luminance: f64 = self.zenith[0] * perez(&self.coefficients[0], d.y, gamma) * LUMINANCE_SCALE;
+        let x: f64 = self.zenith[1] * perez(&self.coefficients[1], d.y, gamma);
+        let y: f64 = self.zenith[2] * perez(&self.coefficients[2], d.y, gamma);
+        let xyz: Color = Color::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        let rgb: Color = spectrum::xyz_to_linear_srgb(&xyz);
+        Color::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+    }
+}
+
+// Daylight: the Preetham sky plus the sun disc seen through the same atmosphere
+#[derive(Debug, Clone)]
+pub struct Sky {
+    model: Preetham,
+    sun_direction: Vec3,
+    sun_radiance: Color,
+    intensity: f64,
+    // Sky without the sun, for importance sampling
+    table: EnvironmentMap,
+    // Chance of sampling the sun disc rather than the table
+    sun_probability: f64,
+}
+
+impl Sky {
+    // `elevation` above the horizon and `azimuth` from +x towards +z, in degrees
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64, intensity: f64) -> Self {
+        let (elevation, azimuth): (f64, f64) = (elevation.to_radians(), azimuth.to_radians());
+        let sun_direction: Vec3 = Vec3::new(elevation.cos() * azimuth.cos(), elevation.sin(), elevation.cos() * azimuth.sin());
+        let model: Preetham = Preetham::new(&sun_direction, turbidity);
+        let sun_radiance: Color = Self::sun_radiance(PI / 2.0 - elevation, turbidity);
+
+        let mut pixels: Vec<Color> = Vec::with_capacity(TABLE_WIDTH * TABLE_HEIGHT);
+        for y in 0..TABLE_HEIGHT {
+            for x in 0..TABLE_WIDTH {
+                let (u, v): (f64, f64) = ((x as f64 + 0.5) / TABLE_WIDTH as f64, 1.0 - (y as f64 + 0.5) / TABLE_HEIGHT as f64);
+                pixels.push(model.value(&uv_to_direction(u, v, 0.0)));
+            }
+        }
+        let table: EnvironmentMap = EnvironmentMap::from_image(ImageTexture::from_pixels(TABLE_WIDTH, TABLE_HEIGHT, pixels), 0.0, 1.0);
+        let sun_power: f64 = luminance(&sun_radiance) * 2.0 * PI * (1.0 - SUN_ANGULAR_RADIUS.cos());
+        let sky_power: f64 = table.power();
+        let sun_probability: f64 = if sun_power > 0.0 { (sun_power / (sun_power + sky_power)).clamp(0.1, 0.9) } else { 0.0 };
+        Self { model, sun_direction, sun_radiance, intensity, table, sun_probability }
+    }
+
+    // Sun seen through `optical_mass` of air: Rayleigh and aerosol (Angstrom) extinction
+    // of a black body, before the sky intensity
+    fn sun_radiance(theta_s: f64, turbidity: f64) -> Color {
+        if theta_s >= PI / 2.0 {
+            return Color::default();
+        }
+        let m: f64 = optical_mass(theta_s);
+        let beta: f64 = 0.04608 * turbidity - 0.04586;
+        let transmittance = |wavelength: f64| -> f64 {
+            let micrometers: f64 = wavelength * 1e-3;
+            (-0.008735 * micrometers.powf(-4.08) * m).exp() * (-beta * micrometers.powf(-1.3) * m).exp()
+        };
+        let unattenuated: f64 = spectrum::spectrum_to_xyz(|wavelength| spectrum::planck(wavelength, SUN_TEMPERATURE)).y;
+        let xyz: Color = spectrum::spectrum_to_xyz(|wavelength| spectrum::planck(wavelength, SUN_TEMPERATURE) * transmittance(wavelength));
+        let rgb: Color = spectrum::xyz_to_linear_srgb(&(xyz * (SUN_LUMINANCE * LUMINANCE_SCALE / unattenuated)));
+        Color::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+    }
+
+    fn in_sun(&self, d: &Vec3) -> bool {
+        d.dot(&self.sun_direction) >= SUN_ANGULAR_RADIUS.cos()
+    }
+
+    pub fn value(&self, direction: &Vec3) -> Color {
+        let d: Vec3 = direction.normalize();
+        let sun: Color = if self.in_sun(&d) { self.sun_radiance } else { Color::default() };
+        (self.model.value(&d) + sun) * self.intensity
+    }
+
+    pub fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let d: Vec3 = direction.normalize();
+        let sun_pdf: f64 = if self.in_sun(&d) { 1.0 / (2.0 * PI * (1.0 - SUN_ANGULAR_RADIUS.cos())) } else { 0.0 };
+        self.sun_probability * sun_pdf + (1.0 - self.sun_probability) * self.table.pdf_value(&d)
+    }
+
+    pub fn random(&self, rng: &mut PRNG) -> Vec3 {
+        if rng.gen::<f64>() >= self.sun_probability {
+            return self.table.random(rng);
+        }
+        // Uniform in the cone of the sun disc
+        let cos_theta: f64 = 1.0 - rng.gen::<f64>() * (1.0 - SUN_ANGULAR_RADIUS.cos());
+        let sin_theta: f64 = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi: f64 = 2.0 * PI * rng.gen::<f64>();
+        Onb::build_from_w(&self.sun_direction).local(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+    }
+}
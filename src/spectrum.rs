@@ -0,0 +1,48 @@
+use crate::vec3::Color;
+
+// Piecewise gaussian fit of the CIE 1931 color matching functions (Wyman et al. 2013)
+fn gaussian(x: f64, mu: f64, sigma1: f64, sigma2: f64) -> f64 {
+    let t: f64 = (x - mu) / (if x < mu { sigma1 } else { sigma2 });
+    (-0.5 * t * t).exp()
+}
+
+fn cie_xyz(wavelength: f64) -> Color {
+    let x: f64 = 1.056 * gaussian(wavelength, 599.8, 37.9, 31.0) + 0.362 * gaussian(wavelength, 442.0, 16.0, 26.7)
+        - 0.065 * gaussian(wavelength, 501.1, 20.4, 26.2);
+    let y: f64 = 0.821 * gaussian(wavelength, 568.8, 46.9, 40.5) + 0.286 * gaussian(wavelength, 530.9, 16.3, 31.1);
+    let z: f64 = 1.217 * gaussian(wavelength, 437.0, 11.8, 36.0) + 0.681 * gaussian(wavelength, 459.0, 26.0, 13.8);
+    Color::new(x, y, z)
+}
+
+pub fn xyz_to_linear_srgb(xyz: &Color) -> Color {
+    Color::new(3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+               -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+               0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z)
+}
+
+// Planck's law, spectral radiance at `wavelength` nm
+pub fn planck(wavelength: f64, kelvin: f64) -> f64 {
+    let c: f64 = 299792458.0;
+    let h: f64 = 6.62606957e-34;
+    let kb: f64 = 1.3806488e-23;
+    let l: f64 = wavelength * 1e-9;
+    (2.0 * h * c * c) / (l.powi(5) * ((h * c / (l * kb * kelvin)).exp() - 1.0))
+}
+
+// CIE XYZ of a spectrum given per wavelength in nm, summed over 380-780 nm in 5 nm steps
+pub fn spectrum_to_xyz<F: Fn(f64) -> f64>(spectrum: F) -> Color {
+    let mut xyz: Color = Color::default();
+    let mut wavelength: f64 = 380.0;
+    while wavelength <= 780.0 {
+        xyz = xyz + cie_xyz(wavelength) * spectrum(wavelength);
+        wavelength += 5.0;
+    }
+    xyz
+}
+
+// Linear sRGB color of a black body at `kelvin`, normalized to unit luminance
+pub fn blackbody(kelvin: f64) -> Color {
+    let xyz: Color = spectrum_to_xyz(|wavelength| planck(wavelength, kelvin));
+    let rgb: Color = xyz_to_linear_srgb(&(xyz / xyz.y));
+    Color::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+}
@@ -1,9 +1,18 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
 use crate::vec3::{Vec3, Point3};
 use crate::hittable::*;
+use crate::aabb::Aabb;
+use crate::light_tree::LightBounds;
+use crate::onb::Onb;
 use crate::ray::Ray;
 use crate::material::Material;
+use crate::texture::Footprint;
+use crate::PRNG;
 
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct Sphere {
     pub center: Point3,
     pub radius: f64,
@@ -16,9 +25,28 @@ pub fn get_sphere_uv(p: &Point3) -> (f64, f64) {
     (phi / (2.0 * std::f64::consts::PI), theta / std::f64::consts::PI)
 }
 
+// dp/du and dp/dv of the `get_sphere_uv` parameterization at unit normal `p`, the dual basis
+// of the surface gradients of u and v. u turns around the x axis and v around the y axis,
+// so the tangents are zero where the parameterization is singular (y = z = 0 and y = ±1).
+pub fn get_sphere_tangents(p: &Point3, radius: f64) -> (Vec3, Vec3) {
+    let distance_squared: f64 = p.y * p.y + p.z * p.z;
+    let sin_theta: f64 = (1.0 - p.y * p.y).max(0.0).sqrt();
+    if distance_squared < 1e-12 || sin_theta < 1e-6 {
+        return (Vec3::default(), Vec3::default());
+    }
+    let grad_u: Vec3 = Vec3::new(0.0, p.z, -p.y) / (2.0 * PI * distance_squared);
+    let grad_v: Vec3 = (Vec3::new(0.0, 1.0, 0.0) - *p * p.y) / (PI * sin_theta);
+    let (uu, uv, vv): (f64, f64, f64) = (grad_u.dot(&grad_u), grad_u.dot(&grad_v), grad_v.dot(&grad_v));
+    let det: f64 = uu * vv - uv * uv;
+    if det.abs() < 1e-12 {
+        return (Vec3::default(), Vec3::default());
+    }
+    ((grad_u * vv - grad_v * uv) * (radius / det), (grad_v * uu - grad_u * uv) * (radius / det))
+}
+
 impl Sphere {
     pub fn new(center: &Vec3, radius: f64, material: &Material) -> Self {
-        Self { center: *center, radius: radius, material: *material }
+        Self { center: *center, radius: radius, material: material.clone() }
     }
 }
 
@@ -41,13 +69,21 @@ impl Hittable for Sphere {
                     let normal: Vec3 = (p - self.center) / self.radius;
                     let front_face: bool = r.direction.dot(&normal) < 0.0;
                     let (u, v): (f64, f64) = get_sphere_uv(&normal);
+                    let (dpdu, dpdv): (Vec3, Vec3) = get_sphere_tangents(&normal, self.radius);
 
                     return Some(HitRecord {
                         t: *root,
                         u: u,
                         v: v,
                         p: p,
+                        object_p: p,
                         normal: if front_face { normal } else { -normal },
+                        dpdu,
+                        dpdv,
+                        // n = (p - center) / radius, flipped with the normal on the inside
+                        dndu: if front_face { dpdu / self.radius } else { -dpdu / self.radius },
+                        dndv: if front_face { dpdv / self.radius } else { -dpdv / self.radius },
+                        footprint: Footprint::default(),
                         front_face: front_face,
                         material: &self.material,
                     });
@@ -57,4 +93,44 @@ impl Hittable for Sphere {
         }
         None
     }
+
+    // Outside the sphere the visible cap is sampled uniformly by solid angle,
+    // from inside (e.g. an emissive dome) the whole surface by area
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let distance_squared: f64 = (self.center - *origin).sqlen();
+        let radius_squared: f64 = self.radius * self.radius;
+        match self.hit(&Ray::new(origin, direction, 0.0), 0.001, f64::INFINITY) {
+            Some(_) if distance_squared > radius_squared => {
+                let cos_theta_max: f64 = (1.0 - radius_squared / distance_squared).sqrt();
+                1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+            }
+            Some(rec) => {
+                let area: f64 = 4.0 * PI * radius_squared;
+                let distance_squared: f64 = rec.t * rec.t * direction.sqlen();
+                let cosine: f64 = (direction.dot(&rec.normal) / direction.len()).abs();
+                distance_squared / (cosine * area)
+            }
+            None => 0.0
+        }
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut PRNG) -> Vec3 {
+        let direction: Vec3 = self.center - *origin;
+        let distance_squared: f64 = direction.sqlen();
+        let radius_squared: f64 = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return self.center + Vec3::random_unit_vector(rng) * self.radius.abs() - *origin;
+        }
+        let cos_theta_max: f64 = (1.0 - radius_squared / distance_squared).sqrt();
+        let z: f64 = 1.0 + rng.gen::<f64>() * (cos_theta_max - 1.0);
+        let phi: f64 = 2.0 * PI * rng.gen::<f64>();
+        let sin_theta: f64 = (1.0 - z * z).max(0.0).sqrt();
+        Onb::build_from_w(&direction).local(&Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+    }
+
+    fn light_bounds(&self) -> Option<LightBounds> {
+        let extent: Vec3 = Vec3::new(self.radius, self.radius, self.radius);
+        Some(LightBounds { bounds: Aabb::new(&(self.center - extent), &(self.center + extent)),
+                           power: self.material.emitted_power(4.0 * PI * self.radius * self.radius) })
+    }
 }
@@ -1,21 +1,309 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use image::ImageError;
+
 use crate::vec3::{Vec3, Point3, Color};
+use crate::hittable::HitRecord;
+use crate::image_texture::{ImageTexture, WrapMode};
+use crate::perlin::Perlin;
+use crate::cellular::{Worley, CellularFeature};
+
+// Screen space derivatives of the texture coordinates at a hit, i.e. the area a
+// pixel covers. All zero (the default) samples a single point.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Footprint {
+    pub dudx: f64,
+    pub dvdx: f64,
+    pub dudy: f64,
+    pub dvdy: f64,
+    pub dpdx: Vec3,
+    pub dpdy: Vec3,
+}
+
+impl Footprint {
+    // Widest extent in (u, v)
+    pub fn uv_width(&self) -> f64 {
+        self.dudx.abs().max(self.dudy.abs()).max(self.dvdx.abs()).max(self.dvdy.abs())
+    }
+
+    // Widest extent in space
+    pub fn p_width(&self) -> f64 {
+        self.dpdx.len().max(self.dpdy.len())
+    }
+}
 
-#[derive(Debug, Copy, Clone)]
+// Coordinates a texture is looked up with
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureSpace {
+    // Surface coordinates, 3D textures see the point (u, v, 0)
+    Uv,
+    // Position in the scene, 2D textures are projected from above with (u, v) = (x, z)
+    World,
+    // Position in the frame of the object before it was moved, so that the
+    // pattern sticks to it. 2D textures are projected like World.
+    Object,
+}
+
+// Textures are nodes of a graph: the combinators below hold their inputs
+// behind `Arc`, so cloning a texture (or a material using it) is cheap
+#[derive(Debug, Clone)]
 pub enum Texture {
+    Solid { color: Color },
     Checker { even: Color, odd: Color },
+    Checker3d { even: Color, odd: Color, size: f64 },
+    UvChecker { even: Color, odd: Color, squares_u: f64, squares_v: f64 },
+    Image { image: Arc<ImageTexture>, wrap: WrapMode },
+    Noise { noise: Arc<Perlin>, scale: f64 },
+    Turbulence { noise: Arc<Perlin>, scale: f64, depth: u32 },
+    Marble { noise: Arc<Perlin>, scale: f64 },
+    Cellular { worley: Worley, scale: f64, feature: CellularFeature, low: Color, high: Color },
+    Wood { noise: Arc<Perlin>, rings: f64, distortion: f64, light: Color, dark: Color },
+    Brick { brick: Color, mortar: Color, width: f64, height: f64, mortar_width: f64, stagger: f64 },
+    UvTransform { texture: Arc<Texture>, scale_u: f64, scale_v: f64, offset_u: f64, offset_v: f64, cos_theta: f64, sin_theta: f64 },
+    Space { texture: Arc<Texture>, space: TextureSpace },
+    Mix { first: Arc<Texture>, second: Arc<Texture>, mask: Arc<Texture> },
+    Multiply { first: Arc<Texture>, second: Arc<Texture> },
+    Add { first: Arc<Texture>, second: Arc<Texture> },
+    Remap { texture: Arc<Texture>, from_min: f64, from_max: f64, to_min: f64, to_max: f64 },
+}
+
+// Blends a point sampled two color pattern towards its average once the
+// footprint, measured in cells, gets wider than half a cell
+fn fade_to_average(point: &Color, even: &Color, odd: &Color, cells: f64) -> Color {
+    let t: f64 = ((cells - 0.5) * 2.0).clamp(0.0, 1.0);
+    *point * (1.0 - t) + (*even + *odd) * (0.5 * t)
 }
 
 impl Texture {
-    pub fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+    // Value at a hit, filtered over its footprint and with the object space point
+    // available to the graph
+    pub fn sample(&self, rec: &HitRecord) -> Color {
+        self.evaluate(rec.u, rec.v, &rec.p, &rec.object_p, &rec.footprint)
+    }
+
+    // Unfiltered value at a single point
+    pub fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.evaluate(u, v, p, p, &Footprint::default())
+    }
+
+    fn evaluate(&self, u: f64, v: f64, p: &Point3, object_p: &Point3, footprint: &Footprint) -> Color {
         match self {
+            Self::Solid { color } => {
+                *color
+            }
             Self::Checker { even, odd } => {
                 let sines: f64 = (p.x * 10.0).sin() * (p.y * 10.0).sin() * (p.z * 10.0).sin();
-                if sines < 0.0 { *odd } else { *even }
+                let point: Color = if sines < 0.0 { *odd } else { *even };
+                fade_to_average(&point, even, odd, footprint.p_width() * 10.0 / std::f64::consts::PI)
+            }
+            Self::Checker3d { even, odd, size } => {
+                // Nudged so faces lying exactly on a cell boundary (a box whose sides are multiples
+                // of `size`) fall in one cell instead of flipping with rounding noise
+                let index = |x: f64| (x / size + 1e-6).floor() as i64;
+                let cell: i64 = index(p.x) + index(p.y) + index(p.z);
+                let point: Color = if cell.rem_euclid(2) == 0 { *even } else { *odd };
+                fade_to_average(&point, even, odd, footprint.p_width() / size)
+            }
+            Self::UvChecker { even, odd, squares_u, squares_v } => {
+                // Box filtered over the footprint in closed form (pbrt's Checkerboard)
+                let s: f64 = u * squares_u;
+                let t: f64 = v * squares_v;
+                let ds: f64 = footprint.dudx.abs().max(footprint.dudy.abs()) * squares_u;
+                let dt: f64 = footprint.dvdx.abs().max(footprint.dvdy.abs()) * squares_v;
+                if (s - ds).floor() == (s + ds).floor() && (t - dt).floor() == (t + dt).floor() {
+                    let cell: i64 = s.floor() as i64 + t.floor() as i64;
+                    return if cell.rem_euclid(2) == 0 { *even } else { *odd };
+                }
+                // Integral of the 1D square wave that is 1 on odd cells
+                let bump_int = |x: f64| (x / 2.0).floor() + 2.0 * (x / 2.0 - (x / 2.0).floor() - 0.5).max(0.0);
+                let s_odd: f64 = (bump_int(s + ds) - bump_int(s - ds)) / (2.0 * ds).max(1e-12);
+                let t_odd: f64 = (bump_int(t + dt) - bump_int(t - dt)) / (2.0 * dt).max(1e-12);
+                let odd_area: f64 = if ds > 1.0 || dt > 1.0 { 0.5 } else { s_odd + t_odd - 2.0 * s_odd * t_odd };
+                *even * (1.0 - odd_area) + *odd * odd_area
+            }
+            Self::Image { image, wrap } => {
+                image.sample_filtered(u, v, *wrap, footprint.uv_width())
+            }
+            Self::Noise { noise, scale } => {
+                Color::new(1.0, 1.0, 1.0) * 0.5 * (1.0 + noise.noise(&(*p * *scale)))
+            }
+            Self::Turbulence { noise, scale, depth } => {
+                Color::new(1.0, 1.0, 1.0) * noise.turb(&(*p * *scale), *depth)
+            }
+            Self::Marble { noise, scale } => {
+                // Veins along z, phase shifted by turbulence
+                Color::new(1.0, 1.0, 1.0) * 0.5 * (1.0 + (scale * p.z + 10.0 * noise.turb(p, 7)).sin())
+            }
+            Self::Cellular { worley, scale, feature, low, high } => {
+                let t: f64 = worley.value(&(*p * *scale), *feature);
+                *low * (1.0 - t) + *high * t
+            }
+            Self::Wood { noise, rings, distortion, light, dark } => {
+                // Rings around the y axis, wobbled by turbulence; the dark latewood
+                // is the last part of every ring
+                let radius: f64 = (p.x * p.x + p.z * p.z).sqrt();
+                let ring: f64 = radius * rings + distortion * noise.turb(p, 4);
+                let t: f64 = ((ring - ring.floor() - 0.6) / 0.3).clamp(0.0, 1.0);
+                let latewood: f64 = t * t * (3.0 - 2.0 * t);
+                *light * (1.0 - latewood) + *dark * latewood
+            }
+            Self::Brick { brick, mortar, width, height, mortar_width, stagger } => {
+                // Rows along u, every other one shifted by `stagger` bricks
+                let row: f64 = (v / height).floor();
+                let shift: f64 = if (row as i64).rem_euclid(2) == 1 { stagger * width } else { 0.0 };
+                let x: f64 = (u + shift).rem_euclid(*width);
+                let y: f64 = v - row * height;
+                if x < *mortar_width || y < *mortar_width { *mortar } else { *brick }
+            }
+            Self::UvTransform { texture, scale_u, scale_v, offset_u, offset_v, cos_theta, sin_theta } => {
+                let rotated_u: f64 = cos_theta * u - sin_theta * v;
+                let rotated_v: f64 = sin_theta * u + cos_theta * v;
+                let transform = |du: f64, dv: f64| ((cos_theta * du - sin_theta * dv) * scale_u, (sin_theta * du + cos_theta * dv) * scale_v);
+                let (dudx, dvdx): (f64, f64) = transform(footprint.dudx, footprint.dvdx);
+                let (dudy, dvdy): (f64, f64) = transform(footprint.dudy, footprint.dvdy);
+                let transformed: Footprint = Footprint { dudx, dvdx, dudy, dvdy, ..*footprint };
+                texture.evaluate(rotated_u * scale_u + offset_u, rotated_v * scale_v + offset_v, p, object_p, &transformed)
+            }
+            Self::Space { texture, space } => {
+                match space {
+                    TextureSpace::Uv => {
+                        let uv: Footprint = Footprint { dpdx: Vec3::new(footprint.dudx, footprint.dvdx, 0.0),
+                                                        dpdy: Vec3::new(footprint.dudy, footprint.dvdy, 0.0),
+                                                        ..*footprint };
+                        texture.evaluate(u, v, &Point3::new(u, v, 0.0), object_p, &uv)
+                    }
+                    TextureSpace::World | TextureSpace::Object => {
+                        let projected: Footprint = Footprint { dudx: footprint.dpdx.x, dvdx: footprint.dpdx.z,
+                                                               dudy: footprint.dpdy.x, dvdy: footprint.dpdy.z,
+                                                               ..*footprint };
+                        let q: &Point3 = if *space == TextureSpace::World { p } else { object_p };
+                        texture.evaluate(q.x, q.z, q, object_p, &projected)
+                    }
+                }
+            }
+            Self::Mix { first, second, mask } => {
+                let t: f64 = mask.evaluate(u, v, p, object_p, footprint).x.clamp(0.0, 1.0);
+                first.evaluate(u, v, p, object_p, footprint) * (1.0 - t) + second.evaluate(u, v, p, object_p, footprint) * t
+            }
+            Self::Multiply { first, second } => {
+                first.evaluate(u, v, p, object_p, footprint) * second.evaluate(u, v, p, object_p, footprint)
+            }
+            Self::Add { first, second } => {
+                first.evaluate(u, v, p, object_p, footprint) + second.evaluate(u, v, p, object_p, footprint)
+            }
+            Self::Remap { texture, from_min, from_max, to_min, to_max } => {
+                let remap = |x: f64| -> f64 {
+                    // Empty input range: a step at `from_min`
+                    if from_max == from_min {
+                        return if x < *from_min { *to_min } else { *to_max };
+                    }
+                    to_min + (x - from_min) / (from_max - from_min) * (to_max - to_min)
+                };
+                let c: Color = texture.evaluate(u, v, p, object_p, footprint);
+                Color::new(remap(c.x), remap(c.y), remap(c.z))
             }
         }
     }
 
+    pub fn new_solid(color: &Color) -> Self {
+        Self::Solid { color: *color }
+    }
+
+    // Grayscale constant, for scalar material inputs
+    pub fn new_scalar(value: f64) -> Self {
+        Self::Solid { color: Color::new(value, value, value) }
+    }
+
     pub fn new_checker(even: &Color, odd: &Color) -> Self {
         Self::Checker { even: *even, odd: *odd }
     }
+
+    // Cubes of side `size` in space, e.g. 50 for the 555 unit Cornell box.
+    // Combine with `new_in_space(.., TextureSpace::Object)` to follow moving objects.
+    pub fn new_checker_3d(even: &Color, odd: &Color, size: f64) -> Self {
+        Self::Checker3d { even: *even, odd: *odd, size }
+    }
+
+    // `squares_u` by `squares_v` squares over the surface coordinates
+    pub fn new_uv_checker(even: &Color, odd: &Color, squares_u: f64, squares_v: f64) -> Self {
+        Self::UvChecker { even: *even, odd: *odd, squares_u, squares_v }
+    }
+
+    // sRGB color image, e.g. `new_image("earthmap.jpg", WrapMode::Repeat)`
+    pub fn new_image<P: AsRef<Path>>(path: P, wrap: WrapMode) -> Result<Self, ImageError> {
+        Ok(Self::Image { image: Arc::new(ImageTexture::load(path, true)?), wrap })
+    }
+
+    // Image holding data rather than color (normal, roughness, opacity maps), not gamma decoded
+    pub fn new_data_image<P: AsRef<Path>>(path: P, wrap: WrapMode) -> Result<Self, ImageError> {
+        Ok(Self::Image { image: Arc::new(ImageTexture::load(path, false)?), wrap })
+    }
+
+    // Smooth Perlin noise of frequency `scale`, the same for the same `seed`
+    pub fn new_noise(seed: u64, scale: f64) -> Self {
+        Self::Noise { noise: Arc::new(Perlin::new(seed)), scale }
+    }
+
+    pub fn new_turbulence(seed: u64, scale: f64, depth: u32) -> Self {
+        Self::Turbulence { noise: Arc::new(Perlin::new(seed)), scale, depth }
+    }
+
+    pub fn new_marble(seed: u64, scale: f64) -> Self {
+        Self::Marble { noise: Arc::new(Perlin::new(seed)), scale }
+    }
+
+    // Rotates the coordinates by `rotation` degrees, then scales and offsets them,
+    // e.g. scale 4 tiles a repeating image four times
+    pub fn new_uv_transform(texture: &Texture, scale_u: f64, scale_v: f64, offset_u: f64, offset_v: f64, rotation: f64) -> Self {
+        let radians: f64 = rotation.to_radians();
+        Self::UvTransform { texture: Arc::new(texture.clone()),
+                            scale_u, scale_v, offset_u, offset_v,
+                            cos_theta: radians.cos(), sin_theta: radians.sin() }
+    }
+
+    pub fn new_in_space(texture: &Texture, space: TextureSpace) -> Self {
+        Self::Space { texture: Arc::new(texture.clone()), space }
+    }
+
+    // `first` where `mask` (red channel) is 0, `second` where it is 1
+    pub fn new_mix(first: &Texture, second: &Texture, mask: &Texture) -> Self {
+        Self::Mix { first: Arc::new(first.clone()), second: Arc::new(second.clone()), mask: Arc::new(mask.clone()) }
+    }
+
+    pub fn new_multiply(first: &Texture, second: &Texture) -> Self {
+        Self::Multiply { first: Arc::new(first.clone()), second: Arc::new(second.clone()) }
+    }
+
+    pub fn new_add(first: &Texture, second: &Texture) -> Self {
+        Self::Add { first: Arc::new(first.clone()), second: Arc::new(second.clone()) }
+    }
+
+    // Maps each channel linearly from [from_min, from_max] to [to_min, to_max], not clamped.
+    // With from_min == from_max it is a threshold: to_min below, to_max from there on.
+    pub fn new_remap(texture: &Texture, from_min: f64, from_max: f64, to_min: f64, to_max: f64) -> Self {
+        Self::Remap { texture: Arc::new(texture.clone()), from_min, from_max, to_min, to_max }
+    }
+
+    // Worley noise of `scale` cells per unit, `feature` blends from `low` to `high`
+    pub fn new_cellular(seed: u64, scale: f64, feature: CellularFeature, low: &Color, high: &Color) -> Self {
+        Self::Cellular { worley: Worley::new(seed), scale, feature, low: *low, high: *high }
+    }
+
+    // Growth rings around the y axis, `rings` per unit, distorted by noise.
+    // Rotate or translate the object (and use object space) to orient the grain.
+    pub fn new_wood(seed: u64, rings: f64, distortion: f64, light: &Color, dark: &Color) -> Self {
+        Self::Wood { noise: Arc::new(Perlin::new(seed)), rings, distortion, light: *light, dark: *dark }
+    }
+
+    // Bricks of `width` by `height` in (u, v), separated by `mortar_width`, in a
+    // running bond (every other row shifted by half a brick)
+    pub fn new_brick(brick: &Color, mortar: &Color, width: f64, height: f64, mortar_width: f64) -> Self {
+        Self::Brick { brick: *brick, mortar: *mortar, width, height, mortar_width, stagger: 0.5 }
+    }
+
+    // Square tiles of side `size` with grout lines of `grout_width`
+    pub fn new_tiles(tile: &Color, grout: &Color, size: f64, grout_width: f64) -> Self {
+        Self::Brick { brick: *tile, mortar: *grout, width: size, height: size, mortar_width: grout_width, stagger: 0.0 }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,252 @@
+use std::ops::{Add, Sub, Mul, Div};
+use std::sync::Arc;
+
+use rand::prelude::Rng;
+
+use crate::vec3::{Vec3, Color};
+use crate::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+use crate::material::{self, Material, ScatterRecord};
+use crate::microfacet;
+use crate::PRNG;
+
+// Wavelengths (nm) averaged into each of the red, green and blue channels
+const WAVELENGTHS: [[f64; 3]; 3] = [[600.0, 640.0, 680.0], [510.0, 540.0, 570.0], [430.0, 460.0, 490.0]];
+
+#[derive(Debug, Copy, Clone)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn norm_squared(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn sqrt(&self) -> Self {
+        let r: f64 = self.norm_squared().sqrt();
+        let re: f64 = ((r + self.re) / 2.0).max(0.0).sqrt();
+        let im: f64 = ((r - self.re) / 2.0).max(0.0).sqrt();
+        Self::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    // e^(i z)
+    fn exp_i(&self) -> Self {
+        let scale: f64 = (-self.im).exp();
+        Self::new(scale * self.re.cos(), scale * self.re.sin())
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self::Output {
+        Self::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self::Output {
+        let d: f64 = other.norm_squared();
+        Self::new((self.re * other.re + self.im * other.im) / d, (self.im * other.re - self.re * other.im) / d)
+    }
+}
+
+// Airy reflectance of a film of index `n1` and `thickness` nm between a medium of index 1
+// and a substrate `n2` (complex for conductors), averaged over both polarizations
+fn airy_reflectance(cos_i: f64, n1: f64, n2: Complex, thickness: f64, wavelength: f64) -> f64 {
+    let one: Complex = Complex::new(1.0, 0.0);
+    let n0: Complex = one;
+    let n1: Complex = Complex::new(n1, 0.0);
+    let cos0: Complex = Complex::new(cos_i, 0.0);
+    let sin2: Complex = Complex::new(1.0 - cos_i * cos_i, 0.0);
+    let cos1: Complex = (one - sin2 / (n1 * n1)).sqrt();
+    let cos2: Complex = (one - sin2 / (n2 * n2)).sqrt();
+
+    let phase: Complex = Complex::new(4.0 * std::f64::consts::PI * thickness / wavelength, 0.0) * n1 * cos1;
+    let shift: Complex = phase.exp_i();
+    let airy = |r01: Complex, r12: Complex| -> f64 {
+        ((r01 + r12 * shift) / (one + r01 * r12 * shift)).norm_squared()
+    };
+
+    let r01_s: Complex = (n0 * cos0 - n1 * cos1) / (n0 * cos0 + n1 * cos1);
+    let r12_s: Complex = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+    let r01_p: Complex = (n1 * cos0 - n0 * cos1) / (n1 * cos0 + n0 * cos1);
+    let r12_p: Complex = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+    (0.5 * (airy(r01_s, r12_s) + airy(r01_p, r12_p))).clamp(0.0, 1.0)
+}
+
+// Complex index of refraction reproducing a metal's normal incidence color
+// (Gulbrandsen 2014, with the edge tint set to the same color)
+fn artist_conductor(reflectivity: f64) -> (f64, f64) {
+    let r: f64 = reflectivity.clamp(0.0, 0.99);
+    let sqrt_r: f64 = r.sqrt();
+    let n: f64 = r * (1.0 - r) / (1.0 + r) + (1.0 - r) * (1.0 + sqrt_r) / (1.0 - sqrt_r);
+    let k: f64 = ((r * (n + 1.0) * (n + 1.0) - (n - 1.0) * (n - 1.0)) / (1.0 - r)).max(0.0).sqrt();
+    (n, k)
+}
+
+// Surface the film is deposited on, indices relative to the side the ray comes from
+enum Substrate {
+    Dielectric { eta: f64, film: f64 },
+    Conductor { eta: Color, k: Color },
+    Metal { albedo: Color },
+}
+
+// Thin coating (soap film, oil slick, anodized metal) whose interference
+// replaces the Fresnel reflectance of the base material by wavelength.
+// `thickness` is read in nanometres from the red channel.
+#[derive(Debug, Clone)]
+pub struct ThinFilm {
+    pub base: Arc<Material>,
+    pub thickness: Texture,
+    pub film_index: f64,
+}
+
+impl Substrate {
+    fn film_reflectance(&self, cos_i: f64, film_index: f64, thickness: f64) -> Color {
+        let channel = |i: usize| -> f64 {
+            let (n1, n2): (f64, Complex) = match self {
+                Self::Dielectric { eta, film } => (*film, Complex::new(*eta, 0.0)),
+                Self::Conductor { eta, k } => (film_index, Complex::new([eta.x, eta.y, eta.z][i], [k.x, k.y, k.z][i])),
+                Self::Metal { albedo } => {
+                    let (n, k): (f64, f64) = artist_conductor([albedo.x, albedo.y, albedo.z][i]);
+                    (film_index, Complex::new(n, k))
+                }
+            };
+            WAVELENGTHS[i].iter().map(|wavelength| airy_reflectance(cos_i, n1, n2, thickness, *wavelength)).sum::<f64>() / 3.0
+        };
+        Color::new(channel(0), channel(1), channel(2))
+    }
+
+    // Reflectance of the uncoated base, which the film replaces
+    fn base_reflectance(&self, cos_i: f64) -> Color {
+        match self {
+            Self::Dielectric { eta, .. } => {
+                let f: f64 = microfacet::fresnel_dielectric(cos_i, *eta);
+                Color::new(f, f, f)
+            }
+            Self::Conductor { eta, k } => microfacet::fresnel_conductor(cos_i, eta, k),
+            Self::Metal { albedo } => *albedo,
+        }
+    }
+}
+
+fn ratio(film: &Color, base: &Color) -> Color {
+    Color::new(film.x / base.x.max(1e-6), film.y / base.y.max(1e-6), film.z / base.z.max(1e-6))
+}
+
+impl ThinFilm {
+    pub fn new(base: &Material, thickness: &Texture, film_index: f64) -> Self {
+        Self { base: Arc::new(base.clone()), thickness: thickness.clone(), film_index }
+    }
+
+    fn substrate(&self, rec: &HitRecord) -> Option<Substrate> {
+        match self.base.as_ref() {
+            Material::Dielectric { refraction_index, .. }
+            | Material::RoughDielectric { refraction_index, .. }
+            | Material::Subsurface { refraction_index, .. } => {
+                let side: f64 = if rec.front_face { 1.0 } else { *refraction_index };
+                let eta: f64 = if rec.front_face { *refraction_index } else { 1.0 / refraction_index };
+                Some(Substrate::Dielectric { eta, film: self.film_index / side })
+            }
+            Material::Conductor { eta, k, .. } => Some(Substrate::Conductor { eta: *eta, k: *k }),
+            Material::Metal { albedo, .. } => Some(Substrate::Metal { albedo: *albedo }),
+            _ => None,
+        }
+    }
+
+    // Replaces the base Fresnel term for light leaving along `direction`
+    fn reweight(&self, r: &Ray, rec: &HitRecord, substrate: &Substrate, direction: &Vec3) -> Color {
+        let wo: Vec3 = -r.direction.normalize();
+        let wi: Vec3 = direction.normalize();
+        let thickness: f64 = self.thickness.sample(rec).x.max(0.0);
+        if wi.dot(&rec.normal) > 0.0 {
+            let cos_i: f64 = wo.dot(&(wo + wi).normalize());
+            ratio(&substrate.film_reflectance(cos_i, self.film_index, thickness), &substrate.base_reflectance(cos_i))
+        } else {
+            let eta: f64 = match substrate {
+                Substrate::Dielectric { eta, .. } => *eta,
+                _ => return Color::default(),
+            };
+            let mut h: Vec3 = (wo + wi * eta).normalize();
+            if h.dot(&rec.normal) < 0.0 {
+                h = -h;
+            }
+            let cos_i: f64 = wo.dot(&h);
+            let one: Color = Color::new(1.0, 1.0, 1.0);
+            ratio(&(one - substrate.film_reflectance(cos_i, self.film_index, thickness)), &(one - substrate.base_reflectance(cos_i)))
+        }
+    }
+
+    pub fn scatter(&self, r: &Ray, rec: &HitRecord, rng: &mut PRNG) -> Option<ScatterRecord> {
+        let substrate: Substrate = match self.substrate(rec) {
+            Some(substrate) => substrate,
+            None => return self.base.scatter(r, rec, rng),
+        };
+        if let (Material::Dielectric { .. }, Substrate::Dielectric { eta, .. }) = (self.base.as_ref(), &substrate) {
+            // Smooth: choose reflection by the film reflectance itself, so that
+            // soap bubbles (a film on an index 1 substrate) still reflect
+            let unit_direction: Vec3 = r.direction.normalize();
+            let cos_i: f64 = (-unit_direction).dot(&rec.normal).min(1.0);
+            let thickness: f64 = self.thickness.sample(rec).x.max(0.0);
+            let reflectance: Color = substrate.film_reflectance(cos_i, self.film_index, thickness);
+            let cannot_refract: bool = (1.0 - cos_i * cos_i).sqrt() > *eta;
+            let probability: f64 = ((reflectance.x + reflectance.y + reflectance.z) / 3.0).clamp(0.0, 1.0);
+            if cannot_refract || rng.gen::<f64>() < probability {
+                let probability: f64 = if cannot_refract { 1.0 } else { probability };
+                let reflected: Vec3 = material::reflect(&unit_direction, &rec.normal);
+                return Some(ScatterRecord { scattered: Ray::new(&rec.p, &reflected, r.time),
+                                            attenuation: reflectance / probability,
+                                            pdf: None });
+            }
+            let direction: Vec3 = material::refract(&unit_direction, &rec.normal, 1.0 / eta);
+            return Some(ScatterRecord { scattered: Ray::new(&rec.p, &direction, r.time),
+                                        attenuation: (Color::new(1.0, 1.0, 1.0) - reflectance) / (1.0 - probability),
+                                        pdf: None });
+        }
+        let srec: ScatterRecord = self.base.scatter(r, rec, rng)?;
+        let weight: Color = self.reweight(r, rec, &substrate, &srec.scattered.direction);
+        Some(ScatterRecord { attenuation: srec.attenuation * weight, ..srec })
+    }
+
+    pub fn eval(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let value: Color = self.base.eval(r, rec, direction);
+        match self.substrate(rec) {
+            Some(substrate) => value * self.reweight(r, rec, &substrate, direction),
+            None => value,
+        }
+    }
+
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.base.pdf(r, rec, direction)
+    }
+
+    pub fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r, rec)
+    }
+}
@@ -3,7 +3,7 @@ use rand::prelude::Rng;
 use std::cmp::PartialEq;
 use crate::PRNG;
 
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -68,6 +68,19 @@ impl Vec3 {
         }
     }
 
+    pub fn random_unit_vector(rng: &mut PRNG) -> Self {
+        Self::random_in_unit_sphere(rng).normalize()
+    }
+
+    // Cosine-weighted direction around +z, pdf = cos(theta) / pi
+    pub fn random_cosine_direction(rng: &mut PRNG) -> Self {
+        let r1: f64 = rng.gen();
+        let r2: f64 = rng.gen();
+        let phi: f64 = 2.0 * std::f64::consts::PI * r1;
+        let z: f64 = (1.0 - r2).sqrt();
+        Self { x: phi.cos() * r2.sqrt(), y: phi.sin() * r2.sqrt(), z }
+    }
+
     pub fn near_zero(&self) -> bool {
         let s: f64 = 1e-8;
         (self.x.abs() < s) && (self.y.abs() < s) && (self.z.abs() < s)